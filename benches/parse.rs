@@ -0,0 +1,43 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use cut::{parse_tsv, TsvView};
+
+fn sample(rows: usize, cols: usize) -> String {
+    let mut raw = String::new();
+    for row in 0..=rows {
+        let line: Vec<String> = (0..cols).map(|col| format!("r{}c{}", row, col)).collect();
+        raw.push_str(&line.join("\t"));
+        raw.push('\n');
+    }
+    raw
+}
+
+fn bench_parse_tsv(c: &mut Criterion) {
+    let raw = sample(100_000, 10);
+    let mut group = c.benchmark_group("parse_tsv");
+    group.throughput(Throughput::Bytes(raw.len() as u64));
+    group.sample_size(20);
+    group.bench_function("100k x 10", |b| b.iter(|| parse_tsv(black_box(&raw), "\t")));
+    group.bench_function("view 100k x 10", |b| {
+        b.iter(|| TsvView::parse(black_box(&raw), "\t"))
+    });
+    group.finish();
+}
+
+fn bench_cut(c: &mut Criterion) {
+    let raw = sample(100_000, 10);
+    let mut group = c.benchmark_group("cut -f1,3");
+    group.throughput(Throughput::Bytes(raw.len() as u64));
+    group.sample_size(20);
+    group.bench_function("parse_tsv + get_cols", |b| {
+        b.iter(|| parse_tsv(black_box(&raw), "\t").get_cols(vec![0, 2]))
+    });
+    group.bench_function("view select", |b| {
+        b.iter(|| TsvView::parse(black_box(&raw), "\t").select(&[0, 2], None))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse_tsv, bench_cut);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+use crate::Table;
+
+pub const CHECKSUM_COLUMN: &str = "checksum";
+
+// CRC-32 (IEEE 802.3, reflected polynomial 0xEDB88320), the same checksum
+// used by zlib and `cksum -a crc32b`. Don't change it: checksums are meant
+// to be compared across runs and versions.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc: u32 = !0;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+impl Table {
+    // Cells are joined with the ASCII unit separator before hashing so that
+    // ["ab", "c"] and ["a", "bc"] get different checksums.
+    pub fn append_row_checksums(&mut self) {
+        self.columns.push(CHECKSUM_COLUMN.into());
+        for row in &mut self.rows {
+            let checksum = crc32(row.join("\u{1f}").as_bytes());
+            row.push(format!("{:08x}", checksum));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::crc32;
+    use crate::parse_tsv;
+
+    #[test]
+    fn crc32_matches_the_standard_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn row_checksums_are_stable_and_track_changes() {
+        let checksums = |raw: &str| -> Vec<String> {
+            let mut table = parse_tsv(raw, "\t");
+            table.append_row_checksums();
+            table.rows.into_iter().map(|x| x[2].clone()).collect()
+        };
+        let before = checksums("a\tb\n1\tx\n2\ty\n");
+        assert_eq!(before, checksums("a\tb\n1\tx\n2\ty\n"));
+        let after = checksums("a\tb\n1\tx\n2\tz\n");
+        assert_eq!(before[0], after[0]);
+        assert_ne!(before[1], after[1]);
+        assert_ne!(checksums("a\tb\nab\tc\n"), checksums("a\tb\na\tbc\n"));
+    }
+}
@@ -0,0 +1,297 @@
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use crate::Table;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    Skip,
+    #[default]
+    Stop,
+}
+
+impl FromStr for ErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(ErrorPolicy::Skip),
+            "stop" => Ok(ErrorPolicy::Stop),
+            x => Err(format!(
+                "unknown error policy '{}', expected skip or stop",
+                x
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub line: usize,
+    pub cells: Vec<String>,
+    pub quoted: Vec<bool>,
+}
+
+pub struct Reader<'a> {
+    raw: &'a str,
+    delimiter: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(raw: &'a str, delimiter: &'a str) -> Self {
+        if delimiter.is_empty() {
+            panic!("CSV delimiter can't be empty");
+        }
+        let raw = raw.strip_prefix('\u{feff}').unwrap_or(raw);
+        Self {
+            raw,
+            delimiter,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.raw[self.pos..]
+    }
+
+    fn end_of_line(&mut self) -> bool {
+        let rest = self.rest();
+        if rest.is_empty() {
+            true
+        } else if rest.starts_with("\r\n") {
+            self.pos += 2;
+            self.line += 1;
+            true
+        } else if rest.starts_with('\n') {
+            self.pos += 1;
+            self.line += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_line(&mut self) {
+        match self.rest().find('\n') {
+            Some(x) => {
+                self.pos += x + 1;
+                self.line += 1;
+            }
+            None => self.pos = self.raw.len(),
+        }
+    }
+
+    fn error(&mut self, line: usize, message: &str) -> CsvError {
+        self.skip_line();
+        CsvError {
+            line,
+            message: message.to_owned(),
+        }
+    }
+
+    fn quoted_cell(&mut self, start_line: usize) -> Result<String, CsvError> {
+        let mut cell = String::new();
+        self.pos += 1;
+        loop {
+            let rest = self.rest();
+            let Some(next) = rest.find('"') else {
+                return Err(CsvError {
+                    line: start_line,
+                    message: "unterminated quoted field".into(),
+                });
+            };
+            cell.push_str(&rest[..next]);
+            self.line += rest[..next].matches('\n').count();
+            self.pos += next + 1;
+            if self.rest().starts_with('"') {
+                cell.push('"');
+                self.pos += 1;
+            } else {
+                return Ok(cell);
+            }
+        }
+    }
+
+    fn unquoted_cell(&mut self) -> String {
+        let rest = self.rest();
+        let end = [rest.find(self.delimiter), rest.find('\n')]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(rest.len());
+        self.pos += end;
+        rest[..end]
+            .strip_suffix('\r')
+            .unwrap_or(&rest[..end])
+            .to_owned()
+    }
+}
+
+impl Iterator for Reader<'_> {
+    type Item = Result<Record, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Blank lines between records, like the usual one at the end of a
+        // file, aren't records.
+        while !self.rest().is_empty() && self.end_of_line() {}
+        if self.rest().is_empty() {
+            return None;
+        }
+
+        let (start, start_line) = (self.pos, self.line);
+        let mut cells: Vec<String> = Vec::new();
+        let mut quoted: Vec<bool> = Vec::new();
+        loop {
+            let is_quoted = self.rest().starts_with('"');
+            let cell = if is_quoted {
+                match self.quoted_cell(start_line) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        // Without a closing quote the rest of the input would
+                        // be one cell, so give up on just the first line.
+                        (self.pos, self.line) = (start, start_line);
+                        self.skip_line();
+                        return Some(Err(e));
+                    }
+                }
+            } else {
+                self.unquoted_cell()
+            };
+            cells.push(cell);
+            quoted.push(is_quoted);
+
+            if self.rest().starts_with(self.delimiter) {
+                self.pos += self.delimiter.len();
+            } else if self.end_of_line() {
+                break;
+            } else {
+                let line = self.line;
+                return Some(Err(
+                    self.error(line, "unexpected character after closing quote")
+                ));
+            }
+        }
+
+        Some(Ok(Record {
+            line: start_line,
+            cells,
+            quoted,
+        }))
+    }
+}
+
+pub fn quote_always(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+pub fn quote<'a>(value: &'a str, delimiter: &str) -> Cow<'a, str> {
+    if value.contains(delimiter) || value.contains(['"', '\n', '\r']) {
+        Cow::Owned(quote_always(value))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+pub fn parse_csv(raw: &str, delimiter: &str, policy: ErrorPolicy, preserve_quotes: bool) -> Table {
+    let mut data: Table = Table {
+        delimiter: delimiter.to_owned(),
+        quoting: true,
+        ..Default::default()
+    };
+    let mut records = Reader::new(raw, delimiter).filter_map(|x| match x {
+        Ok(record) => Some(record),
+        Err(e) if policy == ErrorPolicy::Skip => {
+            let text = raw.lines().nth(e.line - 1).unwrap_or_default();
+            eprintln!("Skipping malformed CSV on {}: {}", e, text);
+            None
+        }
+        Err(e) => panic!("Malformed CSV on {}", e),
+    });
+
+    let header = records.next().expect("Lines should not be empty");
+    if preserve_quotes {
+        data.quoted_cells.push(header.quoted);
+    }
+    data.columns = header.cells;
+    for record in records {
+        data.source_lines.push(record.line);
+        if preserve_quotes {
+            data.quoted_cells.push(record.quoted);
+        }
+        data.rows.push(record.cells);
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "CSV delimiter can't be empty")]
+    fn empty_delimiter_is_rejected() {
+        parse_csv("a,b\n1,2\n", "", ErrorPolicy::Stop, false);
+    }
+
+    #[test]
+    fn quoted_newline_stays_in_one_cell() {
+        let table = parse_csv("a,b\n\"one\ntwo\",3\n4,5\n", ",", ErrorPolicy::Stop, false);
+        assert_eq!(table.rows, vec![vec!["one\ntwo", "3"], vec!["4", "5"]]);
+        assert_eq!(table.source_lines, vec![2, 4]);
+    }
+
+    #[test]
+    fn blank_lines_between_records_are_skipped() {
+        let table = parse_csv("a,b\r\n\r\n1,2\n\n\n3,4\n\n", ",", ErrorPolicy::Stop, false);
+        assert_eq!(table.rows, vec![vec!["1", "2"], vec!["3", "4"]]);
+        assert_eq!(table.source_lines, vec![3, 6]);
+    }
+
+    #[test]
+    fn quote_only_when_needed() {
+        assert_eq!(quote("plain", ","), "plain");
+        assert_eq!(quote("a,b", ","), "\"a,b\"");
+        assert_eq!(quote("say \"hi\"", ","), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn skip_policy_keeps_the_good_rows() {
+        let table = parse_csv("a,b\n1,2\n\"x\"y,3\n4,5\n", ",", ErrorPolicy::Skip, false);
+        assert_eq!(table.rows, vec![vec!["1", "2"], vec!["4", "5"]]);
+
+        let table = parse_csv("a,b\n1,2\n\"x,3\n4,5\n6,7\n", ",", ErrorPolicy::Skip, false);
+        assert_eq!(
+            table.rows,
+            vec![vec!["1", "2"], vec!["4", "5"], vec!["6", "7"]]
+        );
+        assert_eq!(table.source_lines, vec![2, 4, 5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Malformed CSV on line 3: unterminated quoted field")]
+    fn stop_policy_names_the_line() {
+        parse_csv("a,b\n1,2\n\"x,3\n4,5\n", ",", ErrorPolicy::Stop, false);
+    }
+
+    #[test]
+    fn preserved_quotes_round_trip_byte_for_byte() {
+        let raw = "id,\"name\",note\n1,\"ann\",plain\n\"2\",bob,\"a, b\"\n";
+        let table = parse_csv(raw, ",", ErrorPolicy::Stop, true);
+        assert_eq!(table.to_string() + "\n", raw);
+    }
+}
@@ -0,0 +1,38 @@
+use std::io::{stderr, stdin, BufRead, Write};
+
+use cut::Table;
+
+use crate::parse_field_list;
+
+pub fn prompt_fields(data: &Table) -> Vec<u32> {
+    let mut out = stderr().lock();
+    for (index, column) in data.columns.iter().enumerate() {
+        writeln!(out, "{:>3}: {}", index + 1, column).expect("Should be able to write to stderr");
+    }
+
+    let mut lines = stdin().lock().lines();
+    loop {
+        write!(out, "Fields to keep: ").expect("Should be able to write to stderr");
+        out.flush().expect("Should be able to flush stderr");
+
+        let line = match lines.next() {
+            Some(line) => line.expect("Should be able to read from stdin"),
+            None => panic!("No field selection given on stdin"),
+        };
+        match parse_field_list(line.trim()) {
+            Some(fields)
+                if fields
+                    .iter()
+                    .all(|x| *x >= 1 && (*x as usize) <= data.columns.len()) =>
+            {
+                return fields.iter().map(|x| x - 1).collect();
+            }
+            _ => writeln!(
+                out,
+                "Enter field numbers between 1 and {}, e.g. 1,3",
+                data.columns.len()
+            )
+            .expect("Should be able to write to stderr"),
+        }
+    }
+}
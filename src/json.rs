@@ -0,0 +1,478 @@
+use crate::Table;
+
+pub fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn is_number(value: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|x| x.is_ascii_digit());
+    let value = value.strip_prefix('-').unwrap_or(value);
+    let (mantissa, exponent) = match value.find(['e', 'E']) {
+        Some(x) => (&value[..x], Some(&value[x + 1..])),
+        None => (value, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((x, y)) => (x, Some(y)),
+        None => (mantissa, None),
+    };
+    digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(digits)
+        && exponent.is_none_or(|x| digits(x.strip_prefix(['+', '-']).unwrap_or(x)))
+}
+
+impl Table {
+    pub fn numeric_columns(&self, infer_types: bool) -> Vec<bool> {
+        (0..self.columns.len())
+            .map(|index| infer_types && self.is_numeric_column(index))
+            .collect()
+    }
+
+    pub fn json_values(&self, row: &[String], numeric: &[bool]) -> Vec<String> {
+        (0..self.columns.len())
+            .map(|index| match row.get(index) {
+                None => "null".into(),
+                Some(x) if numeric[index] && x.is_empty() => "null".into(),
+                Some(x) if numeric[index] && is_number(x) => x.clone(),
+                Some(x) => escape(x),
+            })
+            .collect()
+    }
+
+    pub fn schema(&self) -> String {
+        let fields: Vec<String> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                format!(
+                    "{}:{}",
+                    escape(column),
+                    escape(self.infer_column_type(index).name())
+                )
+            })
+            .collect();
+        format!("{{{}}}\n", fields.join(","))
+    }
+
+    pub fn to_ndjson(&self, infer_types: bool) -> String {
+        let keys: Vec<String> = self.columns.iter().map(|x| escape(x)).collect();
+        let numeric: Vec<bool> = self.numeric_columns(infer_types);
+        self.rows
+            .iter()
+            .map(|row| {
+                let fields: Vec<String> = keys
+                    .iter()
+                    .zip(self.json_values(row, &numeric))
+                    .map(|(key, value)| format!("{}:{}", key, value))
+                    .collect();
+                format!("{{{}}}", fields.join(","))
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn to_json(&self) -> String {
+        match self {
+            Value::Null => "null".into(),
+            Value::Bool(x) => x.to_string(),
+            Value::Number(x) => x.clone(),
+            Value::String(x) => escape(x),
+            Value::Array(x) => format!(
+                "[{}]",
+                x.iter().map(|x| x.to_json()).collect::<Vec<_>>().join(",")
+            ),
+            Value::Object(x) => format!(
+                "{{{}}}",
+                x.iter()
+                    .map(|(key, value)| format!("{}:{}", escape(key), value.to_json()))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        }
+    }
+
+    fn to_cell(&self) -> String {
+        match self {
+            Value::Null => String::new(),
+            Value::String(x) => x.clone(),
+            x => x.to_json(),
+        }
+    }
+
+    fn flatten_into(&self, prefix: String, out: &mut Vec<(String, String)>) {
+        let children: Vec<(String, &Value)> = match self {
+            Value::Object(x) => x.iter().map(|(key, value)| (key.clone(), value)).collect(),
+            Value::Array(x) => x
+                .iter()
+                .enumerate()
+                .map(|(i, x)| (i.to_string(), x))
+                .collect(),
+            x => return out.push((prefix, x.to_cell())),
+        };
+        if children.is_empty() {
+            return out.push((prefix, self.to_json()));
+        }
+        for (key, value) in children {
+            let key = if prefix.is_empty() {
+                key
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            value.flatten_into(key, out);
+        }
+    }
+}
+
+fn hex_escape(chars: &mut std::str::CharIndices) -> Option<u32> {
+    let hex: String = (0..4).filter_map(|_| chars.next()).map(|x| x.1).collect();
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+struct Parser<'a> {
+    raw: &'a str,
+    pos: usize,
+    newlines: Vec<usize>,
+}
+
+impl Parser<'_> {
+    fn line(&self) -> usize {
+        self.newlines.partition_point(|x| *x < self.pos) + 1
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line(), message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.raw[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.raw[self.pos..].chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        if self.raw[self.pos..].starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        let mut chars = self.raw[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(out);
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, 'r')) => out.push('\r'),
+                    Some((_, 'b')) => out.push('\u{8}'),
+                    Some((_, 'f')) => out.push('\u{c}'),
+                    Some((_, 'u')) => {
+                        let code = hex_escape(&mut chars)
+                            .ok_or_else(|| self.error("invalid unicode escape"))?;
+                        // Characters outside the BMP come as a surrogate pair
+                        // of escapes.
+                        let mut after = chars.clone();
+                        let low = match (after.next(), after.next()) {
+                            (Some((_, '\\')), Some((_, 'u')))
+                                if (0xD800..0xDC00).contains(&code) =>
+                            {
+                                hex_escape(&mut after).filter(|x| (0xDC00..0xE000).contains(x))
+                            }
+                            _ => None,
+                        };
+                        let code = match low {
+                            Some(low) => {
+                                chars = after;
+                                0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)
+                            }
+                            None => code,
+                        };
+                        out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    Some((_, x)) => out.push(x),
+                    None => break,
+                },
+                c => out.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                let mut fields: Vec<(String, Value)> = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.pos += 1;
+                    return Ok(Value::Object(fields));
+                }
+                loop {
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    fields.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => {
+                            self.pos += 1;
+                            return Ok(Value::Object(fields));
+                        }
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some('[') => {
+                self.pos += 1;
+                let mut items: Vec<Value> = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => {
+                            self.pos += 1;
+                            return Ok(Value::Array(items));
+                        }
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('t') => self.literal("true", Value::Bool(true)),
+            Some('f') => self.literal("false", Value::Bool(false)),
+            Some('n') => self.literal("null", Value::Null),
+            Some(_) => {
+                let rest = &self.raw[self.pos..];
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                    .unwrap_or(rest.len());
+                if end == 0 || !is_number(&rest[..end]) {
+                    return Err(self.error("invalid value"));
+                }
+                self.pos += end;
+                Ok(Value::Number(rest[..end].to_owned()))
+            }
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+}
+
+pub fn parse_json(raw: &str, flatten: bool) -> Result<Table, String> {
+    let mut parser = Parser {
+        raw,
+        pos: 0,
+        newlines: raw.match_indices('\n').map(|(x, _)| x).collect(),
+    };
+    let mut records: Vec<(usize, Value)> = Vec::new();
+    parser.skip_whitespace();
+    if parser.peek() == Some('[') {
+        parser.pos += 1;
+        parser.skip_whitespace();
+        if parser.peek() == Some(']') {
+            parser.pos += 1;
+        } else {
+            loop {
+                parser.skip_whitespace();
+                records.push((parser.line(), parser.value()?));
+                parser.skip_whitespace();
+                match parser.peek() {
+                    Some(',') => parser.pos += 1,
+                    Some(']') => {
+                        parser.pos += 1;
+                        break;
+                    }
+                    _ => return Err(parser.error("expected ',' or ']'")),
+                }
+            }
+        }
+        parser.skip_whitespace();
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected input after the closing ']'"));
+        }
+    } else {
+        while parser.peek().is_some() {
+            records.push((parser.line(), parser.value()?));
+            parser.skip_whitespace();
+        }
+    }
+
+    let mut data: Table = Table::default();
+    let mut cells: Vec<Vec<(String, String)>> = Vec::with_capacity(records.len());
+    for (line, record) in records {
+        data.source_lines.push(line);
+        let Value::Object(fields) = record else {
+            return Err(format!(
+                "line {}: expected an object, found {}",
+                line,
+                record.to_json()
+            ));
+        };
+        let row: Vec<(String, String)> = if flatten {
+            let mut out = Vec::new();
+            for (key, value) in fields {
+                value.flatten_into(key, &mut out);
+            }
+            out
+        } else {
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, value.to_cell()))
+                .collect()
+        };
+        for (key, _) in &row {
+            if !data.columns.contains(key) {
+                data.columns.push(key.clone());
+            }
+        }
+        cells.push(row);
+    }
+
+    for row in cells {
+        let mut values: Vec<String> = vec![String::new(); data.columns.len()];
+        for (key, value) in row {
+            let index = data.column_index(&key);
+            values[index] = value;
+        }
+        data.rows.push(values);
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_object_record_names_its_line() {
+        assert_eq!(
+            parse_json("{\"a\": 1}\n2\n", false),
+            Err("line 2: expected an object, found 2".to_owned())
+        );
+    }
+
+    #[test]
+    fn ndjson_infers_numbers_only_when_asked() {
+        let table = crate::parse_tsv("n\tname\n1\tsay \"hi\"\n\t\n", "\t");
+        assert_eq!(
+            table.to_ndjson(true),
+            "{\"n\":1,\"name\":\"say \\\"hi\\\"\"}\n{\"n\":null,\"name\":\"\"}"
+        );
+        assert_eq!(
+            table.to_ndjson(false),
+            "{\"n\":\"1\",\"name\":\"say \\\"hi\\\"\"}\n{\"n\":\"\",\"name\":\"\"}"
+        );
+    }
+
+    #[test]
+    fn collects_keys_across_records() {
+        let raw = "[{\"a\": 1, \"b\": \"x\"}, {\"c\": null, \"a\": true}]";
+        let table = parse_json(raw, false).unwrap();
+        assert_eq!(table.to_string(), "a\tb\tc\n1\tx\t\ntrue\t\t");
+        assert_eq!(table.source_lines, vec![1, 1]);
+    }
+
+    #[test]
+    fn flattens_nested_values_with_dotted_keys() {
+        let raw = "{\"id\": 1, \"user\": {\"name\": \"ann\", \"tags\": [\"a\", \"b\"]}}\n";
+        let table = parse_json(raw, true).unwrap();
+        assert_eq!(
+            table.columns,
+            vec!["id", "user.name", "user.tags.0", "user.tags.1"]
+        );
+        let table = parse_json(raw, false).unwrap();
+        assert_eq!(
+            table.rows[0][1],
+            "{\"name\":\"ann\",\"tags\":[\"a\",\"b\"]}"
+        );
+    }
+
+    #[test]
+    fn decodes_surrogate_pairs() {
+        let table = parse_json("{\"e\": \"\\ud83d\\ude00 \\u00e9\"}", false).unwrap();
+        assert_eq!(table.rows[0][0], "\u{1f600} \u{e9}");
+        let table = parse_json("{\"e\": \"\\ud83d!\"}", false).unwrap();
+        assert_eq!(table.rows[0][0], "\u{fffd}!");
+    }
+
+    #[test]
+    fn rejects_input_after_the_array() {
+        assert_eq!(
+            parse_json("[{\"a\":1}] garbage", false),
+            Err("line 1: unexpected input after the closing ']'".to_owned())
+        );
+        assert!(parse_json("[{\"a\":1}]\n\n", false).is_ok());
+        assert!(parse_json(" [ ] ", false).is_ok());
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_a_line() {
+        let err = parse_json("{\"a\": 1}\n{\"a\" 2}\n", false).unwrap_err();
+        assert!(err.starts_with("line 2: "), "{}", err);
+    }
+
+    #[test]
+    fn schema_names_inferred_types() {
+        let table = crate::parse_tsv("a\tb\tc\n1\t1.5\tx\n", "\t");
+        assert_eq!(
+            table.schema(),
+            "{\"a\":\"integer\",\"b\":\"number\",\"c\":\"string\"}\n"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use crate::csv::{CsvError, Reader};
+
+pub fn lint_csv(raw: &str, delimiter: &str) -> Vec<CsvError> {
+    let mut issues: Vec<CsvError> = Vec::new();
+    let mut width: Option<usize> = None;
+    for record in Reader::new(raw, delimiter) {
+        let record = match record {
+            Ok(x) => x,
+            Err(e) => {
+                issues.push(e);
+                continue;
+            }
+        };
+
+        for (cell, quoted) in record.cells.iter().zip(&record.quoted) {
+            if !quoted && cell.contains('"') {
+                issues.push(CsvError {
+                    line: record.line,
+                    message: format!("stray quote in unquoted field {}", cell),
+                });
+            }
+        }
+
+        match width {
+            None => width = Some(record.cells.len()),
+            Some(x) if x < record.cells.len() => issues.push(CsvError {
+                line: record.line,
+                message: format!(
+                    "{} fields but the header has {}, is a delimiter inside an unquoted field?",
+                    record.cells.len(),
+                    x
+                ),
+            }),
+            Some(x) if x > record.cells.len() => issues.push(CsvError {
+                line: record.line,
+                message: format!("{} fields but the header has {}", record.cells.len(), x),
+            }),
+            Some(_) => {}
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lint_csv;
+
+    #[test]
+    fn unbalanced_quote_reports_its_line() {
+        let issues = lint_csv("a,b\n1,2\n\"x,3\n4,5\n", ",");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].to_string(), "line 3: unterminated quoted field");
+    }
+
+    #[test]
+    fn reports_stray_quotes_and_widths() {
+        let issues: Vec<String> = lint_csv("a,b\n1,x\"y\n1,2,3\n4\n", ",")
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "line 2: stray quote in unquoted field x\"y",
+                "line 3: 3 fields but the header has 2, is a delimiter inside an unquoted field?",
+                "line 4: 1 fields but the header has 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn clean_file_has_no_issues() {
+        assert!(lint_csv("a,b\n\"1,5\",2\n", ",").is_empty());
+    }
+}
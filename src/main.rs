@@ -1,163 +1,1498 @@
-use core::{fmt, panic};
+mod interactive;
+
+use core::panic;
 use std::{
+    borrow::Cow,
+    collections::VecDeque,
     env::args,
     fs,
-    io::{stdin, Read},
+    io::{stdin, stdout, BufRead, BufReader, BufWriter, Read, Write},
+    process::exit,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+use regex::Regex;
+
+use cut::{
+    checksum,
+    csv::{self, ErrorPolicy},
+    json, lint, parse_tsv,
+    query::{ColumnType, Condition},
+    render::Output,
+    sniff,
+    stats::parse_number,
+    transform::{self, ColumnMatch, ImplodeOptions},
+    unescape, Table, TsvView,
 };
 
 #[derive(Debug, PartialEq, Eq)]
 enum CLIOption {
     Fields(Vec<u32>),
+    Reorder(Vec<String>),
+    FieldNames(Vec<String>),
+    IgnoreCase,
+    FieldDefault(String),
+    AllowMissingFields,
+    FieldsBetween(String, String),
+    Base(u32),
     File(String),
     Delimiter(String),
+    Csv,
+    InputJson,
+    FlattenJson,
+    OnError(ErrorPolicy),
+    NoHeaderOutput,
+    OutDelims(Vec<String>),
+    ShellQuote,
+    CleanWhitespace,
+    HeadTail(usize),
+    DropEmptyColumns,
+    SourceLine,
+    ToFixedWidth,
+    Pretty,
+    Ndjson,
+    InferTypes,
+    SqlInsert,
+    SqlDdl,
+    Html,
+    Yaml,
+    HtmlClass(String),
+    SqlTable(String),
+    WriteSchema(String),
+    Peek,
+    RowChecksum,
+    TableChecksum,
+    AlignNumbersRight,
+    Stats,
+    Interactive,
+    DetectDelim,
+    LintCsv,
+    AssertRectangular,
+    Parallel,
+    MaxLineBytes(usize),
+    Strict,
+    DateColumn(String),
+    DateFormat(String),
+    Sort(String),
+    Where(Condition),
+    Explode(String),
+    ExplodeSep(String),
+    ExplodeDropEmpty,
+    Implode,
+    GroupBy(String),
+    ImplodeCol(String),
+    ImplodeSep(String),
+    ImplodeSort,
+    ImplodeUnique,
+    OutputFile(String),
+    OutputBom,
+    RotateColumns(isize),
+    FillDown(String),
+    Transpose,
+    TransposeKey(String),
+    DedupeHeaders,
+    NumericRows(String, bool),
+    SplitColumn(String, Vec<usize>),
+    Into(Vec<String>),
+    KeepOriginal,
+    MergeColumns(Vec<String>),
+    MergeSep(String),
+    Validate(String, String),
+    AutoDelim,
+    OutputDelimiter(String),
+    PreserveQuotes,
+    MaxBufferBytes(usize),
+    EchoCommand,
+    Follow,
+    KeepNumericColumns,
+    KeepColumnsContaining(String),
+    ColumnMatch(ColumnMatch),
+    ValueCounts(String),
+    RoundtripCheck,
 }
 
-fn parse_options(args: &Vec<String>) -> Vec<CLIOption> {
+fn parse_field_list(list: &str) -> Option<Vec<u32>> {
+    let mut delim = ",";
+    if list.contains(" ") && !list.contains(delim) {
+        delim = " ";
+    }
+    list.split(delim).map(|x| x.parse::<u32>().ok()).collect()
+}
+
+fn next_value<'a>(args: &mut impl Iterator<Item = &'a String>, flag: &str) -> String {
+    args.next()
+        .unwrap_or_else(|| panic!("Missing value for {}", flag))
+        .to_owned()
+}
+
+fn parse_options(args: &[String]) -> Vec<CLIOption> {
     let mut options = Vec::<CLIOption>::new();
 
-    for arg in args {
-        if arg.starts_with("-f") {
-            let mut delim = ",";
-            if arg.contains(" ") && !arg.contains(delim) {
-                delim = " ";
-            }
-            let field_nums: Vec<u32> = arg
-                .strip_prefix("-f")
-                .expect("Inside if it must start with '-f'")
-                .split(delim)
-                .map(|x| {
-                    x.parse::<u32>()
-                        .unwrap_or_else(|_| panic!("Invalid field specified"))
-                })
-                .collect();
-            options.push(CLIOption::Fields(field_nums));
-        } else if arg.starts_with("-d") {
-            let delimiter = arg
-                .strip_prefix("-d")
-                .expect("Inside if it must start with '-d'");
-            options.push(CLIOption::Delimiter(delimiter.to_owned()))
-        } else if !arg.starts_with("-") {
-            options.push(CLIOption::File(arg.to_owned()))
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-header-output" => options.push(CLIOption::NoHeaderOutput),
+            "--out-delims" => {
+                options.push(CLIOption::OutDelims(unescape(&next_value(&mut args, arg))))
+            }
+            "--source-line" => options.push(CLIOption::SourceLine),
+            "--drop-empty-columns" => options.push(CLIOption::DropEmptyColumns),
+            "--head-tail" => {
+                let n = next_value(&mut args, arg)
+                    .parse::<usize>()
+                    .unwrap_or_else(|_| panic!("--head-tail must be a number of rows"));
+                options.push(CLIOption::HeadTail(n));
+            }
+            "--clean-whitespace" => options.push(CLIOption::CleanWhitespace),
+            "--shell-quote" => options.push(CLIOption::ShellQuote),
+            "--on-error" => {
+                let policy = next_value(&mut args, arg)
+                    .parse::<ErrorPolicy>()
+                    .unwrap_or_else(|e| panic!("Invalid --on-error: {}", e));
+                options.push(CLIOption::OnError(policy));
+            }
+            "--to-fixed-width" => options.push(CLIOption::ToFixedWidth),
+            "--align-numbers-right" => options.push(CLIOption::AlignNumbersRight),
+            "--pretty" => options.push(CLIOption::Pretty),
+            "--ndjson" => options.push(CLIOption::Ndjson),
+            "--infer-types" => options.push(CLIOption::InferTypes),
+            "--sql-insert" => options.push(CLIOption::SqlInsert),
+            "--sql-ddl" => options.push(CLIOption::SqlDdl),
+            "--html" => options.push(CLIOption::Html),
+            "--yaml" => options.push(CLIOption::Yaml),
+            "--html-class" => options.push(CLIOption::HtmlClass(next_value(&mut args, arg))),
+            "--table" => options.push(CLIOption::SqlTable(next_value(&mut args, arg))),
+            "--write-schema" => options.push(CLIOption::WriteSchema(next_value(&mut args, arg))),
+            "--peek" => options.push(CLIOption::Peek),
+            "--row-checksum" => options.push(CLIOption::RowChecksum),
+            "--table-checksum" => options.push(CLIOption::TableChecksum),
+            "--input-json" => options.push(CLIOption::InputJson),
+            "--flatten-json" => options.push(CLIOption::FlattenJson),
+            "--csv" => options.push(CLIOption::Csv),
+            "--lint-csv" => options.push(CLIOption::LintCsv),
+            "--detect-delim" => options.push(CLIOption::DetectDelim),
+            "--assert-rectangular" => options.push(CLIOption::AssertRectangular),
+            "--max-line-bytes" => {
+                let limit = next_value(&mut args, arg)
+                    .parse::<usize>()
+                    .unwrap_or_else(|_| panic!("--max-line-bytes must be a number of bytes"));
+                options.push(CLIOption::MaxLineBytes(limit));
+            }
+            "--parallel" => options.push(CLIOption::Parallel),
+            "--interactive" => options.push(CLIOption::Interactive),
+            "--ignore-case" => options.push(CLIOption::IgnoreCase),
+            "--field-default" => options.push(CLIOption::FieldDefault(next_value(&mut args, arg))),
+            "--allow-missing-fields" => options.push(CLIOption::AllowMissingFields),
+            "--fields-between" => {
+                let start = next_value(&mut args, arg);
+                let end = next_value(&mut args, arg);
+                options.push(CLIOption::FieldsBetween(start, end));
+            }
+            "--reorder" => options.push(CLIOption::Reorder(
+                next_value(&mut args, arg)
+                    .split(',')
+                    .map(|x| x.to_owned())
+                    .collect(),
+            )),
+            "--base" => {
+                let base = next_value(&mut args, arg);
+                match base.as_str() {
+                    "0" | "1" => {
+                        options.push(CLIOption::Base(base.parse().expect("Matched digit")))
+                    }
+                    _ => panic!("--base must be 0 or 1"),
+                }
+            }
+            "--stats" => options.push(CLIOption::Stats),
+            "--strict" => options.push(CLIOption::Strict),
+            "--date-column" => options.push(CLIOption::DateColumn(next_value(&mut args, arg))),
+            "--date-format" => options.push(CLIOption::DateFormat(next_value(&mut args, arg))),
+            "--sort" => options.push(CLIOption::Sort(next_value(&mut args, arg))),
+            "--explode" => options.push(CLIOption::Explode(next_value(&mut args, arg))),
+            "--explode-sep" => options.push(CLIOption::ExplodeSep(next_value(&mut args, arg))),
+            "--explode-drop-empty" => options.push(CLIOption::ExplodeDropEmpty),
+            "--implode" => options.push(CLIOption::Implode),
+            "--group-by" => options.push(CLIOption::GroupBy(next_value(&mut args, arg))),
+            "--implode-col" => options.push(CLIOption::ImplodeCol(next_value(&mut args, arg))),
+            "--implode-sep" => options.push(CLIOption::ImplodeSep(next_value(&mut args, arg))),
+            "--implode-sort" => options.push(CLIOption::ImplodeSort),
+            "--implode-unique" => options.push(CLIOption::ImplodeUnique),
+            "-o" => options.push(CLIOption::OutputFile(next_value(&mut args, arg))),
+            "--output-bom" => options.push(CLIOption::OutputBom),
+            "--rotate-columns" => {
+                let n = next_value(&mut args, arg)
+                    .parse::<isize>()
+                    .unwrap_or_else(|_| panic!("--rotate-columns must be a number"));
+                options.push(CLIOption::RotateColumns(n));
+            }
+            "--fill-down" => options.push(CLIOption::FillDown(next_value(&mut args, arg))),
+            "--transpose" => options.push(CLIOption::Transpose),
+            "--transpose-key" => options.push(CLIOption::TransposeKey(next_value(&mut args, arg))),
+            "--dedupe-headers" => options.push(CLIOption::DedupeHeaders),
+            "--numeric-rows" => {
+                options.push(CLIOption::NumericRows(next_value(&mut args, arg), true))
+            }
+            "--non-numeric-rows" => {
+                options.push(CLIOption::NumericRows(next_value(&mut args, arg), false))
+            }
+            "--split-column" => {
+                let spec = next_value(&mut args, arg);
+                let (name, widths) = spec
+                    .rsplit_once(':')
+                    .unwrap_or_else(|| panic!("--split-column must look like col:4,2,2"));
+                let widths: Vec<usize> = widths
+                    .split(',')
+                    .map(|x| x.parse::<usize>().ok().filter(|x| *x > 0))
+                    .collect::<Option<Vec<usize>>>()
+                    .unwrap_or_else(|| panic!("Invalid widths for --split-column: {}", widths));
+                options.push(CLIOption::SplitColumn(name.to_owned(), widths));
+            }
+            "--into" => options.push(CLIOption::Into(
+                next_value(&mut args, arg)
+                    .split(',')
+                    .map(|x| x.to_owned())
+                    .collect(),
+            )),
+            "--keep-original" => options.push(CLIOption::KeepOriginal),
+            "--merge-columns" => options.push(CLIOption::MergeColumns(
+                next_value(&mut args, arg)
+                    .split(',')
+                    .map(|x| x.to_owned())
+                    .collect(),
+            )),
+            "--merge-sep" => options.push(CLIOption::MergeSep(next_value(&mut args, arg))),
+            "--validate" => {
+                let rule = next_value(&mut args, arg);
+                let (name, pattern) = rule
+                    .split_once(':')
+                    .unwrap_or_else(|| panic!("--validate must look like col:regex"));
+                options.push(CLIOption::Validate(name.to_owned(), pattern.to_owned()));
+            }
+            "--auto-delim" => options.push(CLIOption::AutoDelim),
+            "--output-delimiter" => {
+                options.push(CLIOption::OutputDelimiter(next_value(&mut args, arg)))
+            }
+            "--preserve-quotes" => options.push(CLIOption::PreserveQuotes),
+            "--max-buffer-bytes" => {
+                let limit = next_value(&mut args, arg)
+                    .parse::<usize>()
+                    .unwrap_or_else(|_| panic!("--max-buffer-bytes must be a number of bytes"));
+                options.push(CLIOption::MaxBufferBytes(limit));
+            }
+            "--echo-command" => options.push(CLIOption::EchoCommand),
+            "--follow" => options.push(CLIOption::Follow),
+            "--keep-numeric-columns" => options.push(CLIOption::KeepNumericColumns),
+            "--keep-columns-containing" => {
+                options.push(CLIOption::KeepColumnsContaining(next_value(&mut args, arg)))
+            }
+            "--column-match" => {
+                let mode = next_value(&mut args, arg)
+                    .parse::<ColumnMatch>()
+                    .unwrap_or_else(|e| panic!("Invalid --column-match: {}", e));
+                options.push(CLIOption::ColumnMatch(mode));
+            }
+            "--value-counts" => options.push(CLIOption::ValueCounts(next_value(&mut args, arg))),
+            "--roundtrip-check" => options.push(CLIOption::RoundtripCheck),
+            "--where" => {
+                let condition = next_value(&mut args, arg)
+                    .parse::<Condition>()
+                    .unwrap_or_else(|e| panic!("Invalid condition: {}", e));
+                options.push(CLIOption::Where(condition));
+            }
+            _ if arg.starts_with("-F") => {
+                let names: String = match arg.strip_prefix("-F") {
+                    Some("") => next_value(&mut args, arg),
+                    Some(x) => x.to_owned(),
+                    None => unreachable!("Inside if it must start with '-F'"),
+                };
+                options.push(CLIOption::FieldNames(
+                    names.split(',').map(|x| x.to_owned()).collect(),
+                ));
+            }
+            _ if arg.starts_with("-f") => {
+                let field_nums: Vec<u32> = parse_field_list(
+                    arg.strip_prefix("-f")
+                        .expect("Inside if it must start with '-f'"),
+                )
+                .unwrap_or_else(|| panic!("Invalid field specified"));
+                options.push(CLIOption::Fields(field_nums));
+            }
+            _ if arg.starts_with("-d") => {
+                let delimiter = arg
+                    .strip_prefix("-d")
+                    .expect("Inside if it must start with '-d'");
+                options.push(CLIOption::Delimiter(delimiter.to_owned()))
+            }
+            _ if !arg.starts_with("-") => options.push(CLIOption::File(arg.to_owned())),
+            _ => {}
         }
     }
 
     options
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct Table {
-    columns: Vec<String>,
-    rows: Vec<Vec<String>>,
-    delimiter: String,
+#[derive(Debug, Clone)]
+struct Config {
+    fields: Vec<u32>,
+    field_names: Vec<String>,
+    ignore_case: bool,
+    field_default: Option<String>,
+    allow_missing_fields: bool,
+    fields_between: Option<(String, String)>,
+    base: u32,
+    reorder: Vec<String>,
+    delimiter: Option<String>,
+    csv: bool,
+    input_json: bool,
+    flatten_json: bool,
+    on_error: ErrorPolicy,
+    show_header: bool,
+    separators: Vec<String>,
+    shell_quote: bool,
+    clean_whitespace: bool,
+    head_tail: Option<usize>,
+    drop_empty_columns: bool,
+    source_line: bool,
+    output: Output,
+    align_numbers_right: bool,
+    infer_types: bool,
+    sql_table: Option<String>,
+    html_class: Option<String>,
+    write_schema: Option<String>,
+    stats: bool,
+    peek: bool,
+    row_checksum: bool,
+    table_checksum: bool,
+    interactive: bool,
+    detect_delim: bool,
+    lint_csv: bool,
+    assert_rectangular: bool,
+    parallel: bool,
+    max_line_bytes: Option<usize>,
+    strict: bool,
+    date_column: Option<String>,
+    date_format: String,
+    sort: Option<String>,
+    conditions: Vec<Condition>,
+    explode: Option<String>,
+    explode_sep: String,
+    explode_drop_empty: bool,
+    implode: bool,
+    group_by: Option<String>,
+    implode_col: Option<String>,
+    implode_options: ImplodeOptions,
+    output_file: Option<String>,
+    output_bom: bool,
+    rotate_columns: Option<isize>,
+    fill_down: Vec<String>,
+    transpose: bool,
+    transpose_key: Option<String>,
+    dedupe_headers: bool,
+    numeric_rows: Vec<(String, bool)>,
+    split_column: Option<(String, Vec<usize>)>,
+    into: Vec<String>,
+    keep_original: bool,
+    merge_columns: Vec<String>,
+    merge_sep: String,
+    validate: Vec<(String, Regex)>,
+    auto_delim: bool,
+    output_delimiter: Option<String>,
+    preserve_quotes: bool,
+    max_buffer_bytes: Option<usize>,
+    echo_command: bool,
+    follow: bool,
+    keep_numeric_columns: bool,
+    keep_columns_containing: Option<String>,
+    column_match: ColumnMatch,
+    value_counts: Option<String>,
+    roundtrip_check: bool,
+    command: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            fields: vec![],
+            field_names: vec![],
+            ignore_case: false,
+            field_default: None,
+            allow_missing_fields: false,
+            fields_between: None,
+            base: 1,
+            reorder: vec![],
+            delimiter: None,
+            csv: false,
+            input_json: false,
+            flatten_json: false,
+            on_error: ErrorPolicy::Stop,
+            show_header: true,
+            separators: vec![],
+            shell_quote: false,
+            clean_whitespace: false,
+            head_tail: None,
+            drop_empty_columns: false,
+            source_line: false,
+            output: Output::Delimited,
+            align_numbers_right: false,
+            infer_types: false,
+            sql_table: None,
+            html_class: None,
+            write_schema: None,
+            stats: false,
+            peek: false,
+            row_checksum: false,
+            table_checksum: false,
+            interactive: false,
+            detect_delim: false,
+            lint_csv: false,
+            assert_rectangular: false,
+            parallel: false,
+            max_line_bytes: None,
+            strict: false,
+            date_column: None,
+            date_format: "%Y-%m-%d".into(),
+            sort: None,
+            conditions: vec![],
+            explode: None,
+            explode_sep: ";".into(),
+            explode_drop_empty: false,
+            implode: false,
+            group_by: None,
+            implode_col: None,
+            implode_options: ImplodeOptions {
+                separator: ";".into(),
+                ..Default::default()
+            },
+            output_file: None,
+            output_bom: false,
+            rotate_columns: None,
+            fill_down: vec![],
+            transpose: false,
+            transpose_key: None,
+            dedupe_headers: false,
+            numeric_rows: vec![],
+            split_column: None,
+            into: vec![],
+            keep_original: false,
+            merge_columns: vec![],
+            merge_sep: " ".into(),
+            validate: vec![],
+            auto_delim: false,
+            output_delimiter: None,
+            preserve_quotes: false,
+            max_buffer_bytes: None,
+            echo_command: false,
+            follow: false,
+            keep_numeric_columns: false,
+            keep_columns_containing: None,
+            column_match: ColumnMatch::All,
+            value_counts: None,
+            roundtrip_check: false,
+            command: vec![],
+        }
+    }
 }
 
-impl Table {
-    fn get_cols(&self, indices: Vec<u32>) -> Table {
-        let mut data: Table = Table::default();
-        data.delimiter = self.delimiter.clone();
-        for index in &indices {
-            data.columns.push(self.columns[*index as usize].clone());
+static FAILED: AtomicBool = AtomicBool::new(false);
+const STREAM_BATCH_ROWS: usize = 1024;
+const COMMENT_PREFIX: &str = "#";
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+struct LineReader<R> {
+    reader: R,
+    limit: Option<usize>,
+    line: usize,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> LineReader<R> {
+    fn new(reader: R, limit: Option<usize>) -> Self {
+        Self {
+            reader,
+            limit,
+            line: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    // Appends at most one line to buf, never reading more than the line limit
+    // allows, so an overlong line fails before it is buffered.
+    fn fill(&mut self) -> std::io::Result<usize> {
+        let Some(limit) = self.limit else {
+            return self.reader.read_until(b'\n', &mut self.buf);
+        };
+        let room = (limit + 2).saturating_sub(self.buf.len());
+        let read = self
+            .reader
+            .by_ref()
+            .take(room as u64)
+            .read_until(b'\n', &mut self.buf)?;
+        if self.content().len() > limit {
+            panic!(
+                "Line {} is longer than --max-line-bytes {}",
+                self.line + 1,
+                limit
+            );
+        }
+        Ok(read)
+    }
+
+    fn content(&self) -> &[u8] {
+        self.buf
+            .strip_suffix(b"\n")
+            .map(|x| x.strip_suffix(b"\r").unwrap_or(x))
+            .unwrap_or(&self.buf)
+    }
+
+    fn take_line(&mut self) -> String {
+        self.line += 1;
+        let text: String = std::str::from_utf8(self.content())
+            .unwrap_or_else(|e| panic!("Input is not valid UTF-8: {}", e))
+            .to_owned();
+        self.buf.clear();
+        text
+    }
+
+    fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        if self.fill()? == 0 {
+            return Ok(None);
         }
+        Ok(Some(self.take_line()))
+    }
+}
+
+fn read_lines_bounded(reader: impl BufRead, limit: usize) -> String {
+    let mut lines = LineReader::new(reader, Some(limit));
+    let mut raw: Vec<u8> = Vec::new();
+    while lines
+        .fill()
+        .unwrap_or_else(|e| panic!("Couldn't read input: {}", e))
+        > 0
+    {
+        raw.append(&mut lines.buf);
+        lines.line += 1;
+    }
+    String::from_utf8(raw).unwrap_or_else(|e| panic!("Input is not valid UTF-8: {}", e))
+}
+
+fn open_input(filename: Option<&String>) -> Box<dyn BufRead> {
+    match filename {
+        Some(x) => Box::new(BufReader::new(
+            fs::File::open(x).unwrap_or_else(|e| panic!("Couldn't read file: {}", e)),
+        )),
+        None => Box::new(stdin().lock()),
+    }
+}
 
-        for row in &self.rows {
-            let mut res_row: Vec<String> = Vec::new();
-            for index in &indices {
-                res_row.push(row[*index as usize].clone());
+fn read_input(filename: Option<&String>, max_line_bytes: Option<usize>) -> String {
+    if let Some(limit) = max_line_bytes {
+        return read_lines_bounded(open_input(filename), limit);
+    }
+
+    let mut raw: String = String::new();
+    match filename {
+        Some(x) => {
+            raw = fs::read_to_string(x).unwrap_or_else(|e| panic!("Couldn't read file: {}", e))
+        }
+        None => {
+            stdin()
+                .read_to_string(&mut raw)
+                .expect("Should be able to read from stdin");
+        }
+    }
+    raw
+}
+
+fn shell_word(arg: &str) -> Cow<'_, str> {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./,=:+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', "'\\''")))
+    }
+}
+
+// Detected delimiters are resolved against the first input when one is at
+// hand. The streaming paths never detect, since detection needs the whole
+// table, so they echo the options as given.
+fn echo_line(config: &Config, first_input: Option<&str>) -> String {
+    if !config.echo_command || config.detect_delim {
+        return String::new();
+    }
+    let (delimiter, output_delimiter) = match first_input {
+        Some(raw) => resolve_delimiters(raw, config),
+        None => (
+            config.delimiter.clone().unwrap_or_else(|| "\t".into()),
+            None,
+        ),
+    };
+    let mut words: Vec<String> = vec!["cut".into()];
+    let mut args = config.command.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--auto-delim" => words.push(format!("-d{}", delimiter)),
+            "--output-delimiter" => {
+                words.push(arg.clone());
+                if let Some(value) = args.next() {
+                    words.push(match (value.as_str(), &output_delimiter) {
+                        ("auto", Some(x)) => x.to_owned(),
+                        _ => value.clone(),
+                    });
+                }
             }
-            data.rows.push(res_row);
+            _ => words.push(arg.clone()),
         }
+    }
+    let words: Vec<Cow<str>> = words.iter().map(|x| shell_word(x)).collect();
+    format!("{} {}\n", COMMENT_PREFIX, words.join(" "))
+}
 
+fn selected_fields(data: &Table, config: &Config, source_line: Option<u32>) -> Vec<u32> {
+    // Field numbers count from the input's first column, ahead of which
+    // --source-line puts its own.
+    let offset: u32 = u32::from(config.source_line);
+    let mut fields: Vec<u32> = if config.interactive {
+        interactive::prompt_fields(data)
+    } else {
+        config.fields.iter().map(|x| x + offset).collect()
+    };
+    let mut missing: Vec<&str> = Vec::new();
+    for name in &config.field_names {
+        match data.find_column(name, config.ignore_case) {
+            Some(index) => fields.push(index as u32),
+            None => missing.push(name),
+        }
+    }
+    if !missing.is_empty() && !config.allow_missing_fields {
+        panic!(
+            "No such columns: {} (available: {})",
+            missing.join(", "),
+            data.columns.join(", ")
+        );
+    }
+    if let Some((start, end)) = &config.fields_between {
+        fields.extend(data.indices_between(start, end, config.ignore_case));
+    }
+
+    let selecting: bool = config.interactive
+        || !config.fields.is_empty()
+        || !config.field_names.is_empty()
+        || config.fields_between.is_some();
+    if !selecting {
+        fields = (0..data.columns.len() as u32)
+            .filter(|x| Some(*x) != source_line)
+            .collect();
+    }
+    if let Some(index) = source_line {
+        fields.insert(0, index);
+    }
+    fields
+}
+
+fn apply_output_settings(data: &mut Table, config: &Config, output_delimiter: Option<String>) {
+    if let Some(x) = output_delimiter {
+        data.delimiter = x;
+    }
+    data.show_header = config.show_header;
+    data.separators = config.separators.clone();
+    data.shell_quote = config.shell_quote;
+}
+
+fn parse_input(raw: &str, config: &Config, delimiter: &str) -> Table {
+    if config.input_json {
+        let mut data: Table = json::parse_json(raw, config.flatten_json)
+            .unwrap_or_else(|e| panic!("Invalid JSON on {}", e));
+        data.delimiter = delimiter.to_owned();
         data
+    } else if config.csv {
+        csv::parse_csv(raw, delimiter, config.on_error, config.preserve_quotes)
+    } else {
+        parse_tsv(raw, delimiter)
     }
 }
 
-impl Default for Table {
-    fn default() -> Self {
+fn first_difference(a: &Table, b: &Table) -> String {
+    if a.columns != b.columns {
+        return format!("header {:?} became {:?}", a.columns, b.columns);
+    }
+    match a.rows.iter().zip(&b.rows).position(|(x, y)| x != y) {
+        Some(index) => format!(
+            "row {} {:?} became {:?}",
+            index + 1,
+            a.rows[index],
+            b.rows[index]
+        ),
+        None => format!("{} rows became {}", a.rows.len(), b.rows.len()),
+    }
+}
+
+fn resolve_delimiters(raw: &str, config: &Config) -> (String, Option<String>) {
+    let detected =
+        || sniff::detect_delimiter(raw).unwrap_or_else(|| panic!("Couldn't detect a delimiter"));
+    let delimiter: String = match &config.delimiter {
+        Some(x) => x.clone(),
+        None if config.auto_delim => detected(),
+        None if config.csv || config.lint_csv => ",".into(),
+        None => "\t".into(),
+    };
+    let output_delimiter: Option<String> = match config.output_delimiter.as_deref() {
+        Some("auto") => Some(detected()),
+        Some(x) => Some(unescape(x).concat()),
+        None => None,
+    };
+    (delimiter, output_delimiter)
+}
+
+fn process(raw: &str, config: &Config) -> Option<String> {
+    if config.detect_delim {
+        for score in sniff::score_delimiters(raw) {
+            eprintln!(
+                "{}\tfields={}\tconsistency={:.2}",
+                sniff::display_delimiter(&score.delimiter),
+                score.fields,
+                score.consistency
+            );
+        }
+        return None;
+    }
+
+    let (delimiter, output_delimiter) = resolve_delimiters(raw, config);
+
+    if needs_whole_table(config).is_none() {
+        let view: TsvView = TsvView::parse(raw, &delimiter);
+        let fields: Vec<u32> = selected_fields(&view.header(), config, None);
+        let mut result: Table = view.select(&fields, config.field_default.as_deref());
+        apply_output_settings(&mut result, config, output_delimiter);
+        return Some(render(result, config));
+    }
+
+    if config.lint_csv {
+        let issues = lint::lint_csv(raw, &delimiter);
+        for issue in &issues {
+            eprintln!("{}", issue);
+        }
+        if !issues.is_empty() {
+            FAILED.store(true, Ordering::Relaxed);
+        }
+        return None;
+    }
+    let mut data: Table = parse_input(raw, config, &delimiter);
+    if config.roundtrip_check {
+        let mut reparsed: Table = parse_input(&data.to_string(), config, &delimiter);
+        reparsed.source_lines = data.source_lines.clone();
+        if reparsed != data {
+            panic!(
+                "Round trip changed the table: {}",
+                first_difference(&data, &reparsed)
+            );
+        }
+    }
+    if config.dedupe_headers {
+        transform::dedupe_names(&mut data.columns);
+    }
+    if config.assert_rectangular {
+        if let Err(e) = data.validate_rectangular() {
+            panic!(
+                "Table is not rectangular: line {} has {} fields but the header has {}",
+                data.source_lines.get(e.row).copied().unwrap_or(e.row + 2),
+                e.found,
+                e.expected
+            );
+        }
+    }
+    for (name, pattern) in &config.validate {
+        let index = data.column_index(name);
+        let mismatches = data.mismatches(index, pattern);
+        for (line, value) in &mismatches {
+            eprintln!(
+                "line {}: {} value '{}' doesn't match {}",
+                line, name, value, pattern
+            );
+        }
+        if !mismatches.is_empty() && config.strict {
+            FAILED.store(true, Ordering::Relaxed);
+        }
+    }
+    let mut source_line: Option<u32> = if config.source_line {
+        data.prepend_source_line_column();
+        Some(0)
+    } else {
+        None
+    };
+    apply_output_settings(&mut data, config, output_delimiter);
+    for name in &config.fill_down {
+        let index = data.column_index(name);
+        data.fill_down(index);
+    }
+
+    let mut types: Vec<ColumnType> = vec![ColumnType::Text; data.columns.len()];
+    if let Some(name) = &config.date_column {
+        let index = data.column_index(name);
+        types[index] = ColumnType::Date(config.date_format.clone());
+        data.check_dates(index, &types[index], config.strict);
+    }
+    for condition in &config.conditions {
+        data.filter(condition, &types);
+    }
+    for (name, numeric) in &config.numeric_rows {
+        let index = data.column_index(name);
+        data.filter_numeric(index, *numeric);
+    }
+    if let Some(name) = &config.sort {
+        let index = data.column_index(name);
+        data.sort_by_column(index, &types[index]);
+    }
+    if let Some(name) = &config.explode {
+        let index = data.column_index(name);
+        data.explode(index, &config.explode_sep, config.explode_drop_empty);
+    }
+    if config.implode {
+        let (Some(key), Some(column)) = (&config.group_by, &config.implode_col) else {
+            panic!("--implode needs --group-by and --implode-col");
+        };
+        let (key, column) = (data.column_index(key), data.column_index(column));
+        data.implode(key, column, &config.implode_options);
+    }
+    if let Some((name, widths)) = &config.split_column {
+        let index = data.column_index(name);
+        data.split_column(
+            index,
+            widths,
+            &config.into,
+            config.keep_original,
+            config.strict,
+        );
+        // Split and merge move columns around, so follow the line column.
+        let remove: usize = usize::from(!config.keep_original);
+        source_line = source_line
+            .filter(|x| *x as usize != index || config.keep_original)
+            .map(|line| {
+                if line as usize > index {
+                    (line as usize + config.into.len() - remove) as u32
+                } else {
+                    line
+                }
+            });
+    }
+    if !config.merge_columns.is_empty() {
+        let indices: Vec<usize> = config
+            .merge_columns
+            .iter()
+            .map(|x| data.column_index(x))
+            .collect();
+        data.merge_columns(&indices, &config.into[0], &config.merge_sep);
+        source_line = source_line
+            .filter(|x| !indices.contains(&(*x as usize)))
+            .map(|line| {
+                let mut removed: Vec<usize> = indices.clone();
+                removed.sort_unstable();
+                removed.dedup();
+                let before = removed.iter().filter(|x| **x < line as usize).count();
+                line - before as u32 + u32::from(before > 0)
+            });
+    }
+
+    let fields: Vec<u32> = selected_fields(&data, config, source_line);
+    let mut result: Table = data.get_cols_or(fields, config.field_default.as_deref());
+    if !config.reorder.is_empty() {
+        let order: Vec<usize> = config
+            .reorder
+            .iter()
+            .map(|x| result.resolve_column(x, config.base))
+            .collect();
+        result = result.reordered(&order);
+    }
+    if let Some(n) = config.rotate_columns {
+        result = result.rotate_columns(n);
+    }
+    if config.drop_empty_columns {
+        result.drop_empty_columns();
+    }
+    if config.keep_numeric_columns {
+        result.keep_columns_where(config.column_match, |x| parse_number(x).is_some());
+    }
+    if let Some(needle) = &config.keep_columns_containing {
+        result.keep_columns_where(config.column_match, |x| x.contains(needle.as_str()));
+    }
+    if config.clean_whitespace {
+        result.clean_whitespace();
+    }
+    if config.transpose {
+        let key: Option<usize> = config
+            .transpose_key
+            .as_ref()
+            .map(|x| result.resolve_column(x, config.base));
+        result = result.transposed(key);
+    }
+
+    if config.peek {
+        return Some(result.peek());
+    }
+    if let Some(name) = &config.value_counts {
+        let index = result.column_index(name);
+        return Some(render(result.value_counts(index), config));
+    }
+    if config.stats {
+        return Some(render(result.stats(), config));
+    }
+
+    if config.row_checksum {
+        result.append_row_checksums();
+    }
+
+    if let Some(path) = &config.write_schema {
+        fs::write(path, result.schema())
+            .unwrap_or_else(|e| panic!("Couldn't write schema {}: {}", path, e));
+    }
+
+    let output: String = render(result, config);
+    if config.table_checksum {
+        eprintln!("{:08x}", checksum::crc32(output.as_bytes()));
+    }
+    Some(output)
+}
+
+fn sql_table(config: &Config) -> &str {
+    config
+        .sql_table
+        .as_deref()
+        .unwrap_or_else(|| panic!("SQL output needs --table NAME"))
+}
+
+// --head-tail only changes what is shown, so it runs here, after everything
+// that reads the rows.
+fn render(mut table: Table, config: &Config) -> String {
+    if let Some(n) = config.head_tail {
+        table.head_tail(n);
+    }
+    match config.output {
+        Output::Delimited => table.to_string(),
+        Output::FixedWidth => table.to_fixed_width(config.align_numbers_right),
+        Output::Pretty => table.to_pretty(),
+        Output::Ndjson => table.to_ndjson(config.infer_types),
+        Output::SqlInsert => table.to_sql_insert(sql_table(config), config.infer_types),
+        Output::SqlDdl => table.to_sql_ddl(sql_table(config)),
+        Output::Html => table.to_html(config.html_class.as_deref()),
+        Output::Yaml => table.to_yaml(config.infer_types),
+    }
+}
+
+fn process_parallel(filenames: &[String], config: &Config) -> Vec<Option<String>> {
+    let workers: usize = thread::available_parallelism()
+        .map(|x| x.get())
+        .unwrap_or(1)
+        .min(filenames.len());
+    let next: AtomicUsize = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, Option<String>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done: Vec<(usize, Option<String>)> = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(filename) = filenames.get(index) else {
+                            break done;
+                        };
+                        done.push((
+                            index,
+                            process(&read_input(Some(filename), config.max_line_bytes), config),
+                        ));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|x| x.join().expect("Worker thread panicked"))
+            .collect()
+    });
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, output)| output).collect()
+}
+
+fn needs_whole_table(config: &Config) -> Option<&'static str> {
+    [
+        (config.csv, "--csv"),
+        (config.input_json, "--input-json"),
+        (config.lint_csv, "--lint-csv"),
+        (config.detect_delim, "--detect-delim"),
+        (config.auto_delim, "--auto-delim"),
+        (
+            config.output_delimiter.as_deref() == Some("auto"),
+            "--output-delimiter auto",
+        ),
+        (
+            !matches!(config.output, Output::Delimited | Output::Ndjson),
+            "the selected output format",
+        ),
+        (
+            config.output == Output::Ndjson && config.infer_types,
+            "--infer-types",
+        ),
+        (config.dedupe_headers, "--dedupe-headers"),
+        (config.assert_rectangular, "--assert-rectangular"),
+        (!config.validate.is_empty(), "--validate"),
+        (config.source_line, "--source-line"),
+        (!config.fill_down.is_empty(), "--fill-down"),
+        (config.date_column.is_some(), "--date-column"),
+        (!config.conditions.is_empty(), "--where"),
+        (!config.numeric_rows.is_empty(), "--numeric-rows"),
+        (config.sort.is_some(), "--sort"),
+        (config.explode.is_some(), "--explode"),
+        (config.implode, "--implode"),
+        (config.split_column.is_some(), "--split-column"),
+        (!config.merge_columns.is_empty(), "--merge-columns"),
+        (!config.reorder.is_empty(), "--reorder"),
+        (config.rotate_columns.is_some(), "--rotate-columns"),
+        (config.drop_empty_columns, "--drop-empty-columns"),
+        (config.keep_numeric_columns, "--keep-numeric-columns"),
+        (
+            config.keep_columns_containing.is_some(),
+            "--keep-columns-containing",
+        ),
+        (config.clean_whitespace, "--clean-whitespace"),
+        (config.transpose, "--transpose"),
+        (config.peek, "--peek"),
+        (config.stats, "--stats"),
+        (config.value_counts.is_some(), "--value-counts"),
+        (config.roundtrip_check, "--roundtrip-check"),
+        (config.row_checksum, "--row-checksum"),
+        (config.write_schema.is_some(), "--write-schema"),
+        (config.table_checksum, "--table-checksum"),
+    ]
+    .into_iter()
+    .find(|(needed, _)| *needed)
+    .map(|(_, option)| option)
+}
+
+struct RowWriter<'a> {
+    config: &'a Config,
+    header: Table,
+    fields: Vec<u32>,
+}
+
+impl<'a> RowWriter<'a> {
+    fn new(header: &str, config: &'a Config) -> Self {
+        let delimiter: String = config.delimiter.clone().unwrap_or_else(|| "\t".into());
+        let header: Table = parse_tsv(header, &delimiter);
+        let fields: Vec<u32> = selected_fields(&header, config, None);
         Self {
-            columns: vec![],
-            rows: vec![],
-            delimiter: "\t".into(),
+            config,
+            header,
+            fields,
         }
     }
+
+    // Splits each buffered line on its own rather than re-parsing the batch
+    // as one string, which would drop a trailing empty line.
+    fn cut(&self, rows: &[String], show_header: bool) -> Table {
+        let delimiter: &str = &self.header.delimiter;
+        let batch: Table = Table {
+            columns: self.header.columns.clone(),
+            rows: rows
+                .iter()
+                .map(|row| row.split(delimiter).map(|x| x.to_owned()).collect())
+                .collect(),
+            delimiter: delimiter.to_owned(),
+            ..Default::default()
+        };
+        let mut result: Table =
+            batch.get_cols_or(self.fields.clone(), self.config.field_default.as_deref());
+        let output_delimiter = self.config.output_delimiter.as_ref();
+        apply_output_settings(
+            &mut result,
+            self.config,
+            output_delimiter.map(|x| unescape(x).concat()),
+        );
+        result.show_header = show_header;
+        result
+    }
+
+    fn write_header(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        if self.config.show_header && self.config.output == Output::Delimited {
+            write!(out, "{}", self.cut(&[], true))?;
+        }
+        Ok(())
+    }
+
+    fn write_table(&self, out: &mut dyn Write, result: &Table) -> std::io::Result<()> {
+        match self.config.output {
+            Output::Ndjson => writeln!(out, "{}", result.to_ndjson(false)),
+            _ => writeln!(out, "{}", result),
+        }
+    }
+
+    fn write_rows(&self, out: &mut dyn Write, rows: &mut Vec<String>) -> std::io::Result<()> {
+        if !rows.is_empty() {
+            self.write_table(out, &self.cut(rows, false))?;
+            rows.clear();
+        }
+        Ok(())
+    }
 }
 
-impl fmt::Display for Table {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut cols = self.columns.clone().into_iter().peekable();
-        while let Some(col) = cols.next() {
-            print!("{}", col);
-            if !cols.peek().is_none() {
-                print!("{}", self.delimiter);
-            }
+fn stream(reader: impl BufRead, config: &Config, out: &mut dyn Write) -> std::io::Result<()> {
+    let mut lines = LineReader::new(reader, config.max_line_bytes);
+    let header: String = lines.next_line()?.unwrap_or_default();
+    let writer: RowWriter = RowWriter::new(&header, config);
+    writer.write_header(out)?;
+
+    if let Some(n) = config.head_tail {
+        return stream_head_tail(&mut lines, &writer, n, out);
+    }
+    let mut rows: Vec<String> = Vec::with_capacity(STREAM_BATCH_ROWS);
+    while let Some(text) = lines.next_line()? {
+        rows.push(text);
+        if rows.len() == STREAM_BATCH_ROWS {
+            writer.write_rows(out, &mut rows)?;
         }
-        write!(f, "\n")?;
-        let mut rows = self.rows.clone().into_iter().peekable();
-        while let Some(row) = rows.next() {
-            let mut vals = row.into_iter().peekable();
-            while let Some(val) = vals.next() {
-                print!("{}", val);
-                if !vals.peek().is_none() {
-                    print!("{}", self.delimiter);
-                }
+    }
+    writer.write_rows(out, &mut rows)
+}
+
+// Keeps only the first and last n rows in memory, so --head-tail works on
+// inputs over --max-buffer-bytes.
+fn stream_head_tail(
+    lines: &mut LineReader<impl BufRead>,
+    writer: &RowWriter,
+    n: usize,
+    out: &mut dyn Write,
+) -> std::io::Result<()> {
+    let mut head: Vec<String> = Vec::with_capacity(n);
+    let mut tail: VecDeque<String> = VecDeque::with_capacity(n);
+    let mut skipped: bool = false;
+    while let Some(text) = lines.next_line()? {
+        if head.len() < n {
+            head.push(text);
+        } else if n > 0 {
+            if tail.len() == n {
+                tail.pop_front();
+                skipped = true;
             }
-            if !rows.peek().is_none() {
-                print!("\n");
+            tail.push_back(text);
+        } else {
+            skipped = true;
+        }
+    }
+
+    head.extend(tail);
+    if !skipped {
+        return writer.write_rows(out, &mut head);
+    }
+    let mut result: Table = writer.cut(&head, false);
+    let width: usize = result.columns.len().max(1);
+    result
+        .rows
+        .insert(n, vec![transform::ELLIPSIS.to_owned(); width]);
+    writer.write_table(out, &result)
+}
+
+fn follow(filename: &str, config: &Config, out: &mut dyn Write) -> std::io::Result<()> {
+    let file = fs::File::open(filename).unwrap_or_else(|e| panic!("Couldn't read file: {}", e));
+    let mut lines = LineReader::new(BufReader::new(file), config.max_line_bytes);
+    while !lines.buf.ends_with(b"\n") {
+        if lines.fill()? == 0 {
+            thread::sleep(FOLLOW_POLL_INTERVAL);
+        }
+    }
+    let writer: RowWriter = RowWriter::new(&lines.take_line(), config);
+    writer.write_header(out)?;
+
+    let mut rows: Vec<String> = Vec::with_capacity(STREAM_BATCH_ROWS);
+    loop {
+        let read = lines.fill()?;
+        if lines.buf.ends_with(b"\n") {
+            rows.push(lines.take_line());
+            if rows.len() < STREAM_BATCH_ROWS {
+                continue;
             }
         }
-        Ok(())
+        writer.write_rows(out, &mut rows)?;
+        out.flush()?;
+        if read == 0 {
+            thread::sleep(FOLLOW_POLL_INTERVAL);
+        }
     }
 }
 
-fn parse_tsv(raw: String, delimiter: &String) -> Table {
-    let mut data: Table = Table::default();
-    data.delimiter = delimiter.clone();
-    let mut lines = raw.lines().into_iter().peekable();
+fn process_bounded(
+    filename: Option<&String>,
+    budget: usize,
+    config: &Config,
+    out: &mut dyn Write,
+) -> std::io::Result<()> {
+    let mut reader = open_input(filename);
+    let mut head: Vec<u8> = Vec::new();
+    reader
+        .by_ref()
+        .take(budget as u64 + 1)
+        .read_to_end(&mut head)?;
+    if head.len() <= budget {
+        let raw: String = match config.max_line_bytes {
+            Some(limit) => read_lines_bounded(&head[..], limit),
+            None => String::from_utf8(head)
+                .unwrap_or_else(|e| panic!("Input is not valid UTF-8: {}", e)),
+        };
+        if let Some(output) = process(&raw, config) {
+            writeln!(out, "{}", output)?;
+        }
+        return Ok(());
+    }
 
-    let columns: Vec<String> = lines
-        .peek()
-        .expect("Lines should not be empty")
-        .split(delimiter)
-        .map(|x| x.to_owned())
-        .collect();
-    data.columns = columns;
+    if let Some(option) = needs_whole_table(config) {
+        panic!(
+            "{} needs the whole table in memory but the input is over --max-buffer-bytes {}",
+            option, budget
+        );
+    }
+    stream((&head[..]).chain(reader), config, out)
+}
 
-    for line in lines.skip(1) {
-        let row: Vec<String> = line.split(delimiter).map(|x| x.to_owned()).collect();
-        data.rows.push(row);
+fn write_all(out: &mut dyn Write, filenames: &[String], config: &Config) -> std::io::Result<()> {
+    if config.output_bom {
+        write!(out, "\u{feff}")?;
     }
+    let inputs: Vec<Option<&String>> = if filenames.is_empty() {
+        vec![None]
+    } else {
+        filenames.iter().map(Some).collect()
+    };
 
-    data
+    if config.follow {
+        if let Some(option) = needs_whole_table(config) {
+            panic!("--follow can't be combined with {}", option);
+        }
+        if config.head_tail.is_some() {
+            panic!("--follow can't be combined with --head-tail");
+        }
+        write!(out, "{}", echo_line(config, None))?;
+        return follow(&filenames[0], config, out);
+    }
+    if let Some(budget) = config.max_buffer_bytes {
+        write!(out, "{}", echo_line(config, None))?;
+        for filename in inputs {
+            process_bounded(filename, budget, config, out)?;
+        }
+        return out.flush();
+    }
+
+    let outputs: Vec<Option<String>> = if config.parallel && !filenames.is_empty() {
+        if config.echo_command {
+            let first: String = read_input(Some(&filenames[0]), config.max_line_bytes);
+            write!(out, "{}", echo_line(config, Some(&first)))?;
+        }
+        process_parallel(filenames, config)
+    } else {
+        let mut raws = inputs
+            .into_iter()
+            .map(|x| read_input(x, config.max_line_bytes))
+            .peekable();
+        write!(
+            out,
+            "{}",
+            echo_line(config, raws.peek().map(|x| x.as_str()))
+        )?;
+        raws.map(|raw| process(&raw, config)).collect()
+    };
+    for output in outputs.into_iter().flatten() {
+        writeln!(out, "{}", output)?;
+    }
+    out.flush()
 }
 
 fn main() {
     let args: Vec<String> = args().skip(1).collect();
     let options: Vec<CLIOption> = parse_options(&args);
 
-    let mut filename: Option<String> = None;
+    let mut filenames: Vec<String> = Vec::new();
+    let mut config: Config = Config {
+        command: args.clone(),
+        ..Default::default()
+    };
     let mut fields: Vec<u32> = Vec::new();
-    let mut delimiter: String = "\t".into();
     for option in options {
         match option {
-            CLIOption::File(x) => filename = Some(x),
-            CLIOption::Fields(x) => fields = x.iter().map(|y| y - 1).collect(),
-            CLIOption::Delimiter(x) => delimiter = x,
+            CLIOption::File(x) => filenames.push(x),
+            CLIOption::Fields(x) => fields = x,
+            CLIOption::FieldNames(x) => config.field_names.extend(x),
+            CLIOption::IgnoreCase => config.ignore_case = true,
+            CLIOption::FieldDefault(x) => config.field_default = Some(x),
+            CLIOption::AllowMissingFields => config.allow_missing_fields = true,
+            CLIOption::FieldsBetween(x, y) => config.fields_between = Some((x, y)),
+            CLIOption::Base(x) => config.base = x,
+            CLIOption::Reorder(x) => config.reorder = x,
+            CLIOption::Delimiter(x) => config.delimiter = Some(x),
+            CLIOption::Csv => config.csv = true,
+            CLIOption::InputJson => config.input_json = true,
+            CLIOption::FlattenJson => config.flatten_json = true,
+            CLIOption::OnError(x) => config.on_error = x,
+            CLIOption::NoHeaderOutput => config.show_header = false,
+            CLIOption::OutDelims(x) => config.separators = x,
+            CLIOption::ShellQuote => config.shell_quote = true,
+            CLIOption::CleanWhitespace => config.clean_whitespace = true,
+            CLIOption::HeadTail(x) => config.head_tail = Some(x),
+            CLIOption::DropEmptyColumns => config.drop_empty_columns = true,
+            CLIOption::SourceLine => config.source_line = true,
+            CLIOption::ToFixedWidth => config.output = Output::FixedWidth,
+            CLIOption::Pretty => config.output = Output::Pretty,
+            CLIOption::Ndjson => config.output = Output::Ndjson,
+            CLIOption::InferTypes => config.infer_types = true,
+            CLIOption::SqlInsert => config.output = Output::SqlInsert,
+            CLIOption::SqlDdl => config.output = Output::SqlDdl,
+            CLIOption::Html => config.output = Output::Html,
+            CLIOption::Yaml => config.output = Output::Yaml,
+            CLIOption::HtmlClass(x) => config.html_class = Some(x),
+            CLIOption::SqlTable(x) => config.sql_table = Some(x),
+            CLIOption::WriteSchema(x) => config.write_schema = Some(x),
+            CLIOption::Peek => config.peek = true,
+            CLIOption::RowChecksum => config.row_checksum = true,
+            CLIOption::TableChecksum => config.table_checksum = true,
+            CLIOption::AlignNumbersRight => config.align_numbers_right = true,
+            CLIOption::Stats => config.stats = true,
+            CLIOption::Interactive => config.interactive = true,
+            CLIOption::DetectDelim => config.detect_delim = true,
+            CLIOption::LintCsv => config.lint_csv = true,
+            CLIOption::AssertRectangular => config.assert_rectangular = true,
+            CLIOption::Parallel => config.parallel = true,
+            CLIOption::MaxLineBytes(x) => config.max_line_bytes = Some(x),
+            CLIOption::Strict => config.strict = true,
+            CLIOption::DateColumn(x) => config.date_column = Some(x),
+            CLIOption::DateFormat(x) => config.date_format = x,
+            CLIOption::Sort(x) => config.sort = Some(x),
+            CLIOption::Where(x) => config.conditions.push(x),
+            CLIOption::Explode(x) => config.explode = Some(x),
+            CLIOption::ExplodeSep(x) => config.explode_sep = x,
+            CLIOption::ExplodeDropEmpty => config.explode_drop_empty = true,
+            CLIOption::Implode => config.implode = true,
+            CLIOption::GroupBy(x) => config.group_by = Some(x),
+            CLIOption::ImplodeCol(x) => config.implode_col = Some(x),
+            CLIOption::ImplodeSep(x) => config.implode_options.separator = x,
+            CLIOption::ImplodeSort => config.implode_options.sort = true,
+            CLIOption::ImplodeUnique => config.implode_options.unique = true,
+            CLIOption::OutputFile(x) => config.output_file = Some(x),
+            CLIOption::OutputBom => config.output_bom = true,
+            CLIOption::RotateColumns(x) => config.rotate_columns = Some(x),
+            CLIOption::FillDown(x) => config.fill_down.push(x),
+            CLIOption::Transpose => config.transpose = true,
+            CLIOption::TransposeKey(x) => config.transpose_key = Some(x),
+            CLIOption::DedupeHeaders => config.dedupe_headers = true,
+            CLIOption::NumericRows(x, y) => config.numeric_rows.push((x, y)),
+            CLIOption::SplitColumn(x, y) => config.split_column = Some((x, y)),
+            CLIOption::Into(x) => config.into = x,
+            CLIOption::KeepOriginal => config.keep_original = true,
+            CLIOption::MergeColumns(x) => config.merge_columns = x,
+            CLIOption::MergeSep(x) => config.merge_sep = x,
+            CLIOption::PreserveQuotes => config.preserve_quotes = true,
+            CLIOption::MaxBufferBytes(x) => config.max_buffer_bytes = Some(x),
+            CLIOption::EchoCommand => config.echo_command = true,
+            CLIOption::Follow => config.follow = true,
+            CLIOption::KeepNumericColumns => config.keep_numeric_columns = true,
+            CLIOption::KeepColumnsContaining(x) => config.keep_columns_containing = Some(x),
+            CLIOption::ColumnMatch(x) => config.column_match = x,
+            CLIOption::ValueCounts(x) => config.value_counts = Some(x),
+            CLIOption::RoundtripCheck => config.roundtrip_check = true,
+            CLIOption::AutoDelim => config.auto_delim = true,
+            CLIOption::OutputDelimiter(x) => config.output_delimiter = Some(x),
+            CLIOption::Validate(x, y) => {
+                let pattern = Regex::new(&y)
+                    .unwrap_or_else(|e| panic!("Invalid regex for --validate {}: {}", x, e));
+                config.validate.push((x, pattern));
+            }
         }
     }
 
-    let mut raw: String = String::new();
-    match filename {
-        Some(x) => {
-            raw = fs::read_to_string(x).unwrap_or_else(|e| panic!("Couldn't read file: {}", e))
+    config.fields = fields
+        .iter()
+        .map(|x| {
+            x.checked_sub(config.base)
+                .unwrap_or_else(|| panic!("Fields are numbered from {}", config.base))
+        })
+        .collect();
+
+    if config.interactive && filenames.is_empty() {
+        panic!("--interactive needs a file argument so stdin is free for input");
+    }
+    if let Some((_, widths)) = &config.split_column {
+        if widths.len() != config.into.len() {
+            panic!(
+                "--split-column has {} widths but --into names {} columns",
+                widths.len(),
+                config.into.len()
+            );
         }
-        None => {
-            stdin()
-                .read_to_string(&mut raw)
-                .expect("Should be able to read from stdin");
+    }
+    if !config.merge_columns.is_empty() {
+        if config.split_column.is_some() {
+            panic!("--merge-columns can't be combined with --split-column");
+        }
+        if config.into.len() != 1 {
+            panic!("--merge-columns needs a single --into name");
         }
     }
+    if config.roundtrip_check && config.input_json {
+        panic!("--roundtrip-check can't re-parse delimited output as JSON");
+    }
+    if config.preserve_quotes && !config.csv {
+        panic!("--preserve-quotes needs --csv");
+    }
+    if config.interactive && config.parallel {
+        panic!("--interactive can't be combined with --parallel");
+    }
+    if config.follow && filenames.len() != 1 {
+        panic!("--follow needs exactly one file argument");
+    }
+    if config.max_buffer_bytes.is_some() && config.parallel {
+        panic!("--max-buffer-bytes can't be combined with --parallel");
+    }
+    if config.transpose_key.is_some() && !config.transpose {
+        panic!("--transpose-key needs --transpose");
+    }
+    let text_output = matches!(
+        config.output,
+        Output::Delimited | Output::Pretty | Output::FixedWidth
+    );
+    if config.head_tail.is_some() && !text_output {
+        panic!("--head-tail only works with delimited, fixed-width or pretty output");
+    }
+
+    let mut out: Box<dyn Write> = match &config.output_file {
+        Some(path) => {
+            Box::new(BufWriter::new(fs::File::create(path).unwrap_or_else(|e| {
+                panic!("Couldn't create output file {}: {}", path, e)
+            })))
+        }
+        None => Box::new(stdout().lock()),
+    };
+    write_all(&mut out, &filenames, &config)
+        .unwrap_or_else(|e| panic!("Couldn't write output: {}", e));
+
+    if FAILED.load(Ordering::Relaxed) {
+        exit(1);
+    }
+}
 
-    let data: Table = parse_tsv(raw, &delimiter);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let result: Table = data.get_cols(fields);
-    println!("{}", result);
+    #[test]
+    fn line_reader_strips_line_endings() {
+        let mut lines = LineReader::new("a\tb\r\n1\t2\n3".as_bytes(), Some(4));
+        assert_eq!(lines.next_line().unwrap().as_deref(), Some("a\tb"));
+        assert_eq!(lines.next_line().unwrap().as_deref(), Some("1\t2"));
+        assert_eq!(lines.next_line().unwrap().as_deref(), Some("3"));
+        assert_eq!(lines.next_line().unwrap(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Line 1 is longer than --max-line-bytes 10")]
+    fn long_header_fails_before_it_is_buffered() {
+        let header: String = "a".repeat(500);
+        let mut lines = LineReader::new(header.as_bytes(), Some(10));
+        lines.next_line().unwrap();
+    }
 }
@@ -0,0 +1,290 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use regex::Regex;
+
+use crate::{stats::parse_number, Table};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    Date(String),
+}
+
+impl ColumnType {
+    fn parse_date(value: &str, format: &str) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(value, format)
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(value, format)
+                    .ok()
+                    .and_then(|x| x.and_hms_opt(0, 0, 0))
+            })
+    }
+
+    pub fn is_valid(&self, value: &str) -> bool {
+        match self {
+            ColumnType::Text => true,
+            ColumnType::Date(format) => Self::parse_date(value, format).is_some(),
+        }
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        if let ColumnType::Date(format) = self {
+            if let (Some(x), Some(y)) = (Self::parse_date(a, format), Self::parse_date(b, format)) {
+                return x.cmp(&y);
+            }
+        }
+        match (parse_number(a), parse_number(b)) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            _ => a.cmp(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn matches(&self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub column: String,
+    pub op: Op,
+    pub value: String,
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let start = s
+            .find(['=', '!', '<', '>'])
+            .ok_or_else(|| format!("'{}' has no operator", s))?;
+        let (column, rest) = s.split_at(start);
+        let ops = [
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("=", Op::Eq),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+        let (symbol, op) = ops
+            .iter()
+            .find(|(symbol, _)| rest.starts_with(symbol))
+            .ok_or_else(|| format!("'{}' has no operator", s))?;
+        if column.is_empty() {
+            return Err(format!("'{}' has no column", s));
+        }
+
+        Ok(Condition {
+            column: column.to_owned(),
+            op: *op,
+            value: rest[symbol.len()..].to_owned(),
+        })
+    }
+}
+
+// A missing cell on a short row reads as empty.
+fn cell(row: &[String], index: usize) -> &str {
+    row.get(index).map_or("", |x| x.as_str())
+}
+
+impl Table {
+    fn retain_rows(&mut self, mut keep: impl FnMut(&Vec<String>) -> bool) {
+        if self.quoted_line(0).is_none() {
+            self.rows.retain(keep);
+            return;
+        }
+        let mut quoted = self.quoted_cells.split_off(1).into_iter();
+        let mut kept: Vec<Vec<bool>> = Vec::new();
+        self.rows.retain(|row| {
+            let flags = quoted.next().unwrap_or_default();
+            let retained = keep(row);
+            if retained {
+                kept.push(flags);
+            }
+            retained
+        });
+        self.quoted_cells.extend(kept);
+    }
+
+    pub fn check_dates(&mut self, index: usize, ty: &ColumnType, strict: bool) {
+        if strict {
+            let invalid = self
+                .rows
+                .iter()
+                .position(|row| !ty.is_valid(cell(row, index)));
+            if let Some(number) = invalid {
+                panic!(
+                    "Invalid date '{}' on line {}",
+                    cell(&self.rows[number], index),
+                    self.source_lines.get(number).copied().unwrap_or(number + 2)
+                );
+            }
+        }
+        self.retain_rows(|row| ty.is_valid(cell(row, index)));
+    }
+
+    pub fn filter(&mut self, condition: &Condition, types: &[ColumnType]) {
+        let index = self.column_index(&condition.column);
+        let ty = &types[index];
+        self.retain_rows(|row| {
+            condition
+                .op
+                .matches(ty.compare(cell(row, index), &condition.value))
+        });
+    }
+
+    pub fn filter_numeric(&mut self, index: usize, numeric: bool) {
+        self.retain_rows(|row| row.get(index).and_then(|x| parse_number(x)).is_some() == numeric);
+    }
+
+    pub fn mismatches(&self, index: usize, pattern: &Regex) -> Vec<(usize, &str)> {
+        self.rows
+            .iter()
+            .enumerate()
+            .filter_map(|(number, row)| {
+                let value = cell(row, index);
+                let line = self.source_lines.get(number).copied().unwrap_or(number + 2);
+                (!pattern.is_match(value)).then_some((line, value))
+            })
+            .collect()
+    }
+
+    pub fn sort_by_column(&mut self, index: usize, ty: &ColumnType) {
+        if self.quoted_line(0).is_none() {
+            self.rows
+                .sort_by(|a, b| ty.compare(cell(a, index), cell(b, index)));
+            return;
+        }
+        let quoted = self.quoted_cells.split_off(1);
+        let mut rows: Vec<(Vec<String>, Vec<bool>)> = std::mem::take(&mut self.rows)
+            .into_iter()
+            .zip(quoted)
+            .collect();
+        rows.sort_by(|a, b| ty.compare(cell(&a.0, index), cell(&b.0, index)));
+        for (row, quoted) in rows {
+            self.rows.push(row);
+            self.quoted_cells.push(quoted);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{csv, csv::ErrorPolicy, parse_tsv};
+
+    fn dates() -> ColumnType {
+        ColumnType::Date("%Y-%m-%d".into())
+    }
+
+    #[test]
+    fn sorts_dates_chronologically() {
+        let mut table = parse_tsv("d\n2021-03-01\n2020-12-31\n2021-01-15\n", "\t");
+        table.sort_by_column(0, &dates());
+        assert_eq!(table.to_string(), "d\n2020-12-31\n2021-01-15\n2021-03-01");
+    }
+
+    #[test]
+    fn filters_dates_after_a_bound() {
+        let mut table = parse_tsv("d\n2019-06-01\n2020-02-01\n2021-01-15\n", "\t");
+        let condition: Condition = "d>2020-01-01".parse().unwrap();
+        table.filter(&condition, &[dates()]);
+        assert_eq!(table.to_string(), "d\n2020-02-01\n2021-01-15");
+    }
+
+    #[test]
+    fn check_dates_skips_unparseable_values() {
+        let mut table = parse_tsv("d\n2020-01-01\nsoon\n", "\t");
+        table.check_dates(0, &dates(), false);
+        assert_eq!(table.to_string(), "d\n2020-01-01");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid date 'soon' on line 3")]
+    fn check_dates_strict_names_the_line() {
+        let mut table = parse_tsv("d\n2020-01-01\nsoon\n", "\t");
+        table.check_dates(0, &dates(), true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid date 'soon' on line 4")]
+    fn check_dates_strict_counts_lines_inside_quoted_cells() {
+        let raw = "n,d\n\"two\nlines\",2020-01-01\nx,soon\n";
+        let mut table = csv::parse_csv(raw, ",", ErrorPolicy::Stop, false);
+        table.check_dates(1, &dates(), true);
+    }
+
+    #[test]
+    fn short_rows_read_as_empty() {
+        let mut table = parse_tsv("a\td\n1\t2021-01-01\n2\n3\t2020-01-01\n", "\t");
+        table.sort_by_column(1, &dates());
+        assert_eq!(table.rows[0], vec!["2"]);
+        let condition: Condition = "d>2020-06-01".parse().unwrap();
+        table.filter(&condition, &[ColumnType::Text, dates()]);
+        assert_eq!(table.to_string(), "a\td\n1\t2021-01-01");
+        table.check_dates(1, &dates(), true);
+    }
+
+    #[test]
+    fn parses_conditions() {
+        let condition: Condition = "age<=30".parse().unwrap();
+        assert_eq!(
+            condition,
+            Condition {
+                column: "age".into(),
+                op: Op::Le,
+                value: "30".into()
+            }
+        );
+        assert!("age".parse::<Condition>().is_err());
+        assert!("=30".parse::<Condition>().is_err());
+    }
+
+    #[test]
+    fn filter_numeric_keeps_or_drops_number_rows() {
+        let raw = "n\n1\ntotal\n2.5\n";
+        let mut table = parse_tsv(raw, "\t");
+        table.filter_numeric(0, true);
+        assert_eq!(table.to_string(), "n\n1\n2.5");
+
+        let mut table = parse_tsv(raw, "\t");
+        table.filter_numeric(0, false);
+        assert_eq!(table.to_string(), "n\ntotal");
+    }
+
+    #[test]
+    fn mismatches_report_source_lines() {
+        let table = parse_tsv("id\n12\nx1\n34\n", "\t");
+        let pattern = Regex::new("^[0-9]+$").unwrap();
+        assert_eq!(table.mismatches(0, &pattern), vec![(3, "x1")]);
+    }
+
+    #[test]
+    fn sort_keeps_quote_flags_with_their_rows() {
+        let mut table = csv::parse_csv("n\n\"2\"\n1\n", ",", ErrorPolicy::Stop, true);
+        table.sort_by_column(0, &ColumnType::Text);
+        assert_eq!(table.to_string(), "n\n1\n\"2\"");
+    }
+}
@@ -0,0 +1,184 @@
+use crate::Table;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Output {
+    #[default]
+    Delimited,
+    FixedWidth,
+    Pretty,
+    Ndjson,
+    SqlInsert,
+    SqlDdl,
+    Html,
+    Yaml,
+}
+
+pub const PEEK_ROWS: usize = 3;
+
+pub fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl Table {
+    pub fn to_html(&self, class: Option<&str>) -> String {
+        let row = |cells: &[String], tag: &str| -> String {
+            let cells: String = cells
+                .iter()
+                .map(|x| format!("<{}>{}</{}>", tag, escape_html(x), tag))
+                .collect();
+            format!("    <tr>{}</tr>", cells)
+        };
+
+        let mut lines: Vec<String> = Vec::new();
+        match class {
+            Some(x) => lines.push(format!("<table class=\"{}\">", escape_html(x))),
+            None => lines.push("<table>".into()),
+        }
+        if self.show_header {
+            lines.push("  <thead>".into());
+            lines.push(row(&self.columns, "th"));
+            lines.push("  </thead>".into());
+        }
+        lines.push("  <tbody>".into());
+        lines.extend(self.rows.iter().map(|x| row(x, "td")));
+        lines.push("  </tbody>".into());
+        lines.push("</table>".into());
+        lines.join("\n")
+    }
+
+    fn column_widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self.columns.iter().map(|x| x.chars().count()).collect();
+        for row in &self.rows {
+            for (index, cell) in row.iter().enumerate().take(widths.len()) {
+                widths[index] = widths[index].max(cell.chars().count());
+            }
+        }
+        widths
+    }
+
+    pub fn to_pretty(&self) -> String {
+        let widths: Vec<usize> = self.column_widths();
+        let pad = |cells: &[String]| -> String {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<String>>()
+                .join(" | ")
+                .trim_end()
+                .to_owned()
+        };
+
+        let mut lines: Vec<String> = Vec::new();
+        if self.show_header {
+            lines.push(pad(&self.columns));
+            lines.push(
+                widths
+                    .iter()
+                    .map(|x| "-".repeat(*x))
+                    .collect::<Vec<String>>()
+                    .join("-+-"),
+            );
+        }
+        lines.extend(self.rows.iter().map(|row| pad(row)));
+        lines.join("\n")
+    }
+
+    pub fn peek(&self) -> String {
+        let head: Table = Table {
+            columns: self.columns.clone(),
+            rows: self.rows.iter().take(PEEK_ROWS).cloned().collect(),
+            ..self.empty_like()
+        };
+        format!(
+            "columns: {}\nrows: {}\n{}",
+            self.columns.len(),
+            self.rows.len(),
+            head.to_pretty()
+        )
+    }
+
+    pub fn to_fixed_width(&self, align_numbers_right: bool) -> String {
+        let widths: Vec<usize> = self.column_widths();
+        let right: Vec<bool> = (0..widths.len())
+            .map(|index| align_numbers_right && self.is_numeric_column(index))
+            .collect();
+
+        let pad = |cells: &[String]| -> String {
+            cells
+                .iter()
+                .zip(widths.iter().zip(&right))
+                .map(|(cell, (width, right))| {
+                    if *right {
+                        format!("{:>width$}", cell, width = width)
+                    } else {
+                        format!("{:<width$}", cell, width = width)
+                    }
+                })
+                .collect()
+        };
+
+        let mut lines: Vec<String> = Vec::new();
+        if self.show_header {
+            lines.push(pad(&self.columns));
+        }
+        lines.extend(self.rows.iter().map(|row| pad(row)));
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape_html;
+    use crate::parse_tsv;
+
+    const TABLE: &str = "name\tqty\nann\t7\nbartholomew\t12\n";
+
+    #[test]
+    fn fixed_width_can_right_align_numbers() {
+        let table = parse_tsv(TABLE, "\t");
+        assert_eq!(
+            table.to_fixed_width(false),
+            "name       qty\nann        7  \nbartholomew12 "
+        );
+        assert_eq!(
+            table.to_fixed_width(true),
+            "name       qty\nann          7\nbartholomew 12"
+        );
+    }
+
+    #[test]
+    fn pretty_pads_and_underlines_the_header() {
+        assert_eq!(
+            parse_tsv(TABLE, "\t").to_pretty(),
+            "name        | qty\n------------+----\nann         | 7\nbartholomew | 12"
+        );
+    }
+
+    #[test]
+    fn peek_shows_the_shape_and_first_rows() {
+        let table = parse_tsv("n\n1\n2\n3\n4\n", "\t");
+        assert_eq!(table.peek(), "columns: 1\nrows: 4\nn\n-\n1\n2\n3");
+    }
+
+    #[test]
+    fn html_escapes_cells_and_the_class() {
+        let table = parse_tsv("a\n<b> & \"c\"\n", "\t");
+        assert_eq!(
+            table.to_html(Some("x\"y")),
+            "<table class=\"x&quot;y\">\n  <thead>\n    <tr><th>a</th></tr>\n  </thead>\n  <tbody>\n    <tr><td>&lt;b&gt; &amp; &quot;c&quot;</td></tr>\n  </tbody>\n</table>"
+        );
+        assert_eq!(escape_html("it's"), "it&#39;s");
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+pub const CANDIDATES: [&str; 5] = ["\t", ",", ";", "|", " "];
+pub const SNIFF_LINES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelimiterScore {
+    pub delimiter: String,
+    pub fields: usize,
+    pub consistency: f64,
+}
+
+pub fn score_delimiters(raw: &str) -> Vec<DelimiterScore> {
+    let lines: Vec<&str> = raw
+        .lines()
+        .filter(|x| !x.is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+
+    let mut scores: Vec<DelimiterScore> = CANDIDATES
+        .iter()
+        .map(|delimiter| {
+            let mut counts: HashMap<usize, usize> = HashMap::new();
+            for line in &lines {
+                *counts.entry(line.split(delimiter).count()).or_default() += 1;
+            }
+            let (fields, matching) = counts
+                .into_iter()
+                .max_by_key(|(fields, matching)| (*matching, *fields))
+                .unwrap_or((1, 0));
+            let consistency = if fields > 1 {
+                matching as f64 / lines.len() as f64
+            } else {
+                0.0
+            };
+            DelimiterScore {
+                delimiter: delimiter.to_string(),
+                fields,
+                consistency,
+            }
+        })
+        .collect();
+
+    scores.sort_by(|a, b| {
+        b.consistency
+            .total_cmp(&a.consistency)
+            .then(b.fields.cmp(&a.fields))
+    });
+    scores
+}
+
+pub fn detect_delimiter(raw: &str) -> Option<String> {
+    score_delimiters(raw)
+        .into_iter()
+        .find(|x| x.consistency > 0.0)
+        .map(|x| x.delimiter)
+}
+
+pub fn display_delimiter(delimiter: &str) -> String {
+    match delimiter {
+        "\t" => "\\t".into(),
+        " " => "' '".into(),
+        x => x.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMAS: &str = "a,b,c\n1,2,3\n4,5;6,7\nx y,z,w\n";
+
+    #[test]
+    fn comma_scores_highest_on_a_comma_file() {
+        let scores = score_delimiters(COMMAS);
+        assert_eq!(scores[0].delimiter, ",");
+        assert_eq!(scores[0].fields, 3);
+        assert_eq!(scores[0].consistency, 1.0);
+        assert!(scores[1..].iter().all(|x| x.consistency < 1.0));
+        assert_eq!(detect_delimiter(COMMAS).as_deref(), Some(","));
+    }
+
+    #[test]
+    fn single_column_input_has_no_delimiter() {
+        assert_eq!(detect_delimiter("a\nb\nc\n"), None);
+    }
+}
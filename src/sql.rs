@@ -0,0 +1,142 @@
+use crate::{stats::InferredType, Table};
+
+// Words reserved by the SQL standard or a common engine, which can't be used
+// as bare identifiers.
+const KEYWORDS: &str =
+    "add all alter and any as asc between by case check column constraint create cross \
+     default delete desc distinct drop else end exists foreign from full group having in \
+     index inner insert into is join key left like limit not null on or order outer \
+     primary references right select set table then to union unique update user values \
+     when where";
+
+pub fn quote_identifier(name: &str) -> String {
+    let mut chars = name.chars();
+    let plain = chars
+        .next()
+        .is_some_and(|x| x.is_ascii_alphabetic() || x == '_')
+        && chars.all(|x| x.is_ascii_alphanumeric() || x == '_')
+        && !KEYWORDS
+            .split_whitespace()
+            .any(|x| x.eq_ignore_ascii_case(name));
+    if plain {
+        name.to_owned()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+pub fn quote_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl Table {
+    // The quoted SQL name of a column; an empty header becomes column_N so
+    // the DDL and the INSERTs agree.
+    fn sql_column(&self, index: usize) -> String {
+        match self.columns[index].as_str() {
+            "" => format!("column_{}", index + 1),
+            x => quote_identifier(x),
+        }
+    }
+
+    pub fn to_sql_ddl(&self, table: &str) -> String {
+        let columns: Vec<String> = (0..self.columns.len())
+            .map(|index| {
+                let ty = match self.infer_column_type(index) {
+                    InferredType::Integer => "INTEGER",
+                    InferredType::Number => "REAL",
+                    InferredType::String => "TEXT",
+                };
+                format!("    {} {}", self.sql_column(index), ty)
+            })
+            .collect();
+        format!(
+            "CREATE TABLE {} (\n{}\n);",
+            quote_identifier(table),
+            columns.join(",\n")
+        )
+    }
+
+    pub fn to_sql_insert(&self, table: &str, infer_types: bool) -> String {
+        let columns: Vec<String> = (0..self.columns.len())
+            .map(|index| self.sql_column(index))
+            .collect();
+        let numeric: Vec<bool> = self.numeric_columns(infer_types);
+        self.rows
+            .iter()
+            .map(|row| {
+                let values: Vec<String> = row
+                    .iter()
+                    .zip(&numeric)
+                    .map(|(cell, numeric)| {
+                        if cell.is_empty() {
+                            "NULL".into()
+                        } else if *numeric {
+                            cell.trim().to_owned()
+                        } else {
+                            quote_string(cell)
+                        }
+                    })
+                    .collect();
+                format!(
+                    "INSERT INTO {} ({}) VALUES ({});",
+                    quote_identifier(table),
+                    columns.join(", "),
+                    values.join(", ")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::quote_identifier;
+    use crate::parse_tsv;
+
+    #[test]
+    fn inserts_escape_strings_and_null_empty_cells() {
+        let table = parse_tsv("id\tname\n1\tO'Brien\n2\t\n", "\t");
+        assert_eq!(
+            table.to_sql_insert("users", true),
+            "INSERT INTO users (id, name) VALUES (1, 'O''Brien');\n\
+             INSERT INTO users (id, name) VALUES (2, NULL);"
+        );
+        assert_eq!(
+            table.to_sql_insert("users", false),
+            "INSERT INTO users (id, name) VALUES ('1', 'O''Brien');\n\
+             INSERT INTO users (id, name) VALUES ('2', NULL);"
+        );
+    }
+
+    #[test]
+    fn ddl_maps_types_and_quotes_identifiers() {
+        let table = parse_tsv("id\tfull name\t\n1\tann\t0.5\n2\tbob\t1\n", "\t");
+        assert_eq!(
+            table.to_sql_ddl("my users"),
+            "CREATE TABLE \"my users\" (\n    id INTEGER,\n    \"full name\" TEXT,\n    column_3 REAL\n);"
+        );
+    }
+
+    #[test]
+    fn keywords_are_quoted() {
+        let table = parse_tsv("order\tSelect\n1\tx\n", "\t");
+        assert_eq!(
+            table.to_sql_ddl("t"),
+            "CREATE TABLE t (\n    \"order\" INTEGER,\n    \"Select\" TEXT\n);"
+        );
+        assert_eq!(quote_identifier("orders"), "orders");
+        assert_eq!(quote_identifier("2nd"), "\"2nd\"");
+    }
+
+    #[test]
+    fn inserts_name_columns_like_the_ddl() {
+        let table = parse_tsv("id\t\n1\tx\n", "\t");
+        assert_eq!(
+            table.to_sql_insert("t", false),
+            "INSERT INTO t (id, column_2) VALUES ('1', 'x');"
+        );
+        assert!(table.to_sql_ddl("t").contains("    column_2 TEXT"));
+    }
+}
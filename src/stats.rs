@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::Table;
+
+pub fn parse_number(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|x| x.is_finite())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InferredType {
+    Integer,
+    Number,
+    String,
+}
+
+impl InferredType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InferredType::Integer => "integer",
+            InferredType::Number => "number",
+            InferredType::String => "string",
+        }
+    }
+}
+
+impl Table {
+    pub fn infer_column_type(&self, index: usize) -> InferredType {
+        let mut values = self
+            .rows
+            .iter()
+            .filter_map(|row| row.get(index))
+            .filter(|x| !x.is_empty())
+            .peekable();
+        if values.peek().is_none() {
+            return InferredType::String;
+        }
+        let mut inferred = InferredType::Integer;
+        for value in values {
+            if inferred == InferredType::Integer && value.trim().parse::<i64>().is_ok() {
+                continue;
+            }
+            if parse_number(value).is_some() {
+                inferred = InferredType::Number;
+            } else {
+                return InferredType::String;
+            }
+        }
+        inferred
+    }
+
+    pub fn is_numeric_column(&self, index: usize) -> bool {
+        self.infer_column_type(index) != InferredType::String
+    }
+
+    pub fn value_counts(&self, index: usize) -> Table {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for row in &self.rows {
+            let value = row.get(index).map(|x| x.as_str()).unwrap_or_default();
+            *counts.entry(value).or_default() += 1;
+        }
+        let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        let mut data: Table = self.empty_like();
+        data.columns = vec!["value".into(), "count".into()];
+        data.rows = counts
+            .into_iter()
+            .map(|(value, count)| vec![value.to_owned(), count.to_string()])
+            .collect();
+        data
+    }
+
+    pub fn stats(&self) -> Table {
+        let mut data: Table = self.empty_like();
+        data.columns = ["column", "count", "distinct", "min", "max", "mean"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+
+        for (index, column) in self.columns.iter().enumerate() {
+            let values: Vec<&str> = self
+                .rows
+                .iter()
+                .filter_map(|row| row.get(index))
+                .map(|x| x.as_str())
+                .filter(|x| !x.is_empty())
+                .collect();
+            let distinct: HashSet<&str> = values.iter().copied().collect();
+            let numbers: Vec<f64> = values.iter().filter_map(|x| parse_number(x)).collect();
+
+            let mut row: Vec<String> = vec![
+                column.clone(),
+                values.len().to_string(),
+                distinct.len().to_string(),
+            ];
+            if !numbers.is_empty() && numbers.len() == values.len() {
+                let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let mean = numbers.iter().sum::<f64>() / numbers.len() as f64;
+                row.push(min.to_string());
+                row.push(max.to_string());
+                row.push(mean.to_string());
+            } else {
+                row.extend(["", "", ""].iter().map(|x| x.to_string()));
+            }
+            data.rows.push(row);
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InferredType;
+    use crate::parse_tsv;
+
+    const MIXED: &str = "name\tage\tscore\nann\t30\t1.5\nbob\t\t2.5\nann\t40\tx\n";
+
+    #[test]
+    fn stats_summarise_each_column() {
+        let stats = parse_tsv(MIXED, "\t").stats();
+        assert_eq!(
+            stats.to_string(),
+            "column\tcount\tdistinct\tmin\tmax\tmean\n\
+             name\t3\t2\t\t\t\n\
+             age\t2\t2\t30\t40\t35\n\
+             score\t3\t3\t\t\t"
+        );
+    }
+
+    #[test]
+    fn infers_the_narrowest_type() {
+        let table = parse_tsv("a\tb\tc\n1\t1\tx\n2\t2.5\t3\n", "\t");
+        assert_eq!(table.infer_column_type(0), InferredType::Integer);
+        assert_eq!(table.infer_column_type(1), InferredType::Number);
+        assert_eq!(table.infer_column_type(2), InferredType::String);
+    }
+
+    #[test]
+    fn value_counts_sort_by_count_then_value() {
+        let table = parse_tsv("k\nb\na\nd\nc\nb\na\nb\n", "\t");
+        assert_eq!(
+            table.value_counts(0).to_string(),
+            "value\tcount\nb\t3\na\t2\nc\t1\nd\t1"
+        );
+    }
+}
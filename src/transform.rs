@@ -0,0 +1,507 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use crate::Table;
+
+pub const SOURCE_LINE_COLUMN: &str = "line";
+pub const ELLIPSIS: &str = "...";
+pub const TRANSPOSE_LABEL: &str = "column";
+
+pub fn dedupe_names(names: &mut [String]) {
+    let mut taken: HashSet<String> = names.iter().cloned().collect();
+    let mut seen: HashSet<String> = HashSet::with_capacity(names.len());
+    for name in names.iter_mut() {
+        if seen.insert(name.clone()) {
+            continue;
+        }
+        let mut n = 2;
+        while taken.contains(&format!("{}_{}", name, n)) {
+            n += 1;
+        }
+        *name = format!("{}_{}", name, n);
+        taken.insert(name.clone());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ImplodeOptions {
+    pub separator: String,
+    pub sort: bool,
+    pub unique: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnMatch {
+    Any,
+    #[default]
+    All,
+}
+
+impl FromStr for ColumnMatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(ColumnMatch::Any),
+            "all" => Ok(ColumnMatch::All),
+            x => Err(format!("unknown column match '{}', expected any or all", x)),
+        }
+    }
+}
+
+impl Table {
+    // The line column goes first so it stays put however ragged the rows are.
+    pub fn prepend_source_line_column(&mut self) {
+        self.columns.insert(0, SOURCE_LINE_COLUMN.into());
+        for (row, line) in self.rows.iter_mut().zip(&self.source_lines) {
+            row.insert(0, line.to_string());
+        }
+        for flags in &mut self.quoted_cells {
+            flags.insert(0, false);
+        }
+    }
+
+    pub fn head_tail(&mut self, n: usize) {
+        let len: usize = self.rows.len();
+        if len <= 2 * n {
+            return;
+        }
+        let width: usize = self.columns.len().max(1);
+        if self.quoted_cells.len() == len + 1 {
+            self.quoted_cells
+                .splice(n + 1..len + 1 - n, [vec![false; width]]);
+        }
+        self.rows
+            .splice(n..len - n, [vec![ELLIPSIS.to_owned(); width]]);
+    }
+
+    pub fn drop_empty_columns(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let keep: Vec<u32> = (0..self.columns.len())
+            .filter(|index| {
+                self.rows
+                    .iter()
+                    .any(|row| row.get(*index).is_some_and(|x| !x.is_empty()))
+            })
+            .map(|x| x as u32)
+            .collect();
+        if keep.len() != self.columns.len() {
+            *self = self.get_cols(keep);
+        }
+    }
+
+    pub fn keep_columns_where(&mut self, mode: ColumnMatch, predicate: impl Fn(&str) -> bool) {
+        let keep: Vec<u32> = (0..self.columns.len())
+            .filter(|index| {
+                let mut values = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(*index))
+                    .filter(|x| !x.is_empty())
+                    .peekable();
+                match mode {
+                    ColumnMatch::Any => values.any(|x| predicate(x)),
+                    ColumnMatch::All => values.peek().is_some() && values.all(|x| predicate(x)),
+                }
+            })
+            .map(|x| x as u32)
+            .collect();
+        if keep.len() != self.columns.len() {
+            *self = self.get_cols(keep);
+        }
+    }
+
+    pub fn clean_whitespace(&mut self) {
+        let clean = |x: &mut String| *x = x.split_whitespace().collect::<Vec<&str>>().join(" ");
+        self.columns.iter_mut().for_each(clean);
+        self.rows.iter_mut().flatten().for_each(clean);
+    }
+
+    pub fn reordered(&self, order: &[usize]) -> Table {
+        let mut permutation: Vec<u32> = Vec::with_capacity(self.columns.len());
+        for index in order.iter().copied().chain(0..self.columns.len()) {
+            if !permutation.contains(&(index as u32)) {
+                permutation.push(index as u32);
+            }
+        }
+        self.get_cols(permutation)
+    }
+
+    pub fn rotate_columns(&self, n: isize) -> Table {
+        let width: usize = self.columns.len();
+        if width == 0 {
+            return self.get_cols(vec![]);
+        }
+        let shift: usize = n.rem_euclid(width as isize) as usize;
+        self.get_cols(
+            (0..width)
+                .map(|index| ((index + shift) % width) as u32)
+                .collect(),
+        )
+    }
+
+    pub fn fill_down(&mut self, index: usize) {
+        let mut last: Option<String> = None;
+        for row in &mut self.rows {
+            let Some(cell) = row.get_mut(index) else {
+                continue;
+            };
+            if !cell.is_empty() {
+                last = Some(cell.clone());
+            } else if let Some(x) = &last {
+                *cell = x.clone();
+            }
+        }
+    }
+
+    pub fn transposed(&self, key: Option<usize>) -> Table {
+        let cell = |row: &[String], index: usize| row.get(index).cloned().unwrap_or_default();
+        let mut data: Table = self.empty_like();
+        data.columns = match key {
+            Some(key) => std::iter::once(self.columns[key].clone())
+                .chain(self.rows.iter().map(|row| cell(row, key)))
+                .collect(),
+            None => std::iter::once(TRANSPOSE_LABEL.to_owned())
+                .chain((1..=self.rows.len()).map(|x| x.to_string()))
+                .collect(),
+        };
+        dedupe_names(&mut data.columns);
+
+        for (index, column) in self.columns.iter().enumerate() {
+            if Some(index) == key {
+                continue;
+            }
+            data.rows.push(
+                std::iter::once(column.clone())
+                    .chain(self.rows.iter().map(|row| cell(row, index)))
+                    .collect(),
+            );
+        }
+        data
+    }
+
+    pub fn split_column(
+        &mut self,
+        index: usize,
+        widths: &[usize],
+        names: &[String],
+        keep_original: bool,
+        strict: bool,
+    ) {
+        let total: usize = widths.iter().sum();
+        let at: usize = if keep_original { index + 1 } else { index };
+        let remove: usize = if keep_original { 0 } else { 1 };
+        self.quoted_cells.clear();
+        self.columns.splice(at..at + remove, names.iter().cloned());
+        for (number, row) in self.rows.iter_mut().enumerate() {
+            let Some(cell) = row.get(index) else {
+                continue;
+            };
+            let chars: Vec<char> = cell.chars().collect();
+            if chars.len() < total && strict {
+                panic!(
+                    "Cell '{}' on row {} is shorter than the {} characters to split",
+                    cell,
+                    number + 1,
+                    total
+                );
+            }
+            let mut start: usize = 0;
+            let pieces: Vec<String> = widths
+                .iter()
+                .map(|width| {
+                    let end = (start + width).min(chars.len());
+                    let piece: String = chars[start.min(end)..end].iter().collect();
+                    start += width;
+                    piece
+                })
+                .collect();
+            row.splice(at..at + remove, pieces);
+        }
+    }
+
+    pub fn merge_columns(&mut self, indices: &[usize], name: &str, separator: &str) {
+        let Some(at) = indices.iter().copied().min() else {
+            return;
+        };
+        let mut removed: Vec<usize> = indices.to_vec();
+        removed.sort_unstable();
+        removed.dedup();
+        self.quoted_cells.clear();
+
+        for row in &mut self.rows {
+            let merged: String = indices
+                .iter()
+                .map(|x| row.get(*x).map(|x| x.as_str()).unwrap_or_default())
+                .collect::<Vec<&str>>()
+                .join(separator);
+            for index in removed.iter().rev() {
+                if *index < row.len() {
+                    row.remove(*index);
+                }
+            }
+            row.insert(at.min(row.len()), merged);
+        }
+        for index in removed.iter().rev() {
+            self.columns.remove(*index);
+        }
+        self.columns.insert(at, name.to_owned());
+    }
+
+    pub fn explode(&mut self, index: usize, separator: &str, drop_empty: bool) {
+        let mut rows: Vec<Vec<String>> = Vec::with_capacity(self.rows.len());
+        for row in self.rows.drain(..) {
+            let Some(cell) = row.get(index) else {
+                rows.push(row);
+                continue;
+            };
+            let values: Vec<&str> = cell
+                .split(separator)
+                .filter(|x| !drop_empty || !x.is_empty())
+                .collect();
+            if values.is_empty() {
+                let mut kept = row.clone();
+                kept[index].clear();
+                rows.push(kept);
+                continue;
+            }
+            for value in values {
+                let mut exploded = row.clone();
+                exploded[index] = value.to_owned();
+                rows.push(exploded);
+            }
+        }
+        self.rows = rows;
+    }
+
+    pub fn implode(&mut self, key: usize, column: usize, options: &ImplodeOptions) {
+        let mut groups: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for row in self.rows.drain(..) {
+            let cell = |index: usize| row.get(index).cloned().unwrap_or_default();
+            let value = cell(column);
+            match positions.get(&cell(key)) {
+                Some(position) => groups[*position].1.push(value),
+                None => {
+                    positions.insert(cell(key), groups.len());
+                    groups.push((row, vec![value]));
+                }
+            }
+        }
+
+        for (mut row, mut values) in groups {
+            if options.sort {
+                values.sort();
+            }
+            if options.unique {
+                let mut seen: Vec<String> = Vec::new();
+                values.retain(|x| {
+                    let new = !seen.contains(x);
+                    if new {
+                        seen.push(x.clone());
+                    }
+                    new
+                });
+            }
+            let joined: String = values.join(&options.separator);
+            match row.get_mut(column) {
+                Some(cell) => *cell = joined,
+                None if !joined.is_empty() => {
+                    row.resize(column, String::new());
+                    row.push(joined);
+                }
+                None => {}
+            }
+            self.rows.push(row);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{csv, csv::ErrorPolicy, parse_tsv};
+
+    fn numbered(rows: usize) -> String {
+        let mut raw: String = "n".into();
+        for row in 1..=rows {
+            raw.push_str(&format!("\n{}", row));
+        }
+        raw
+    }
+
+    #[test]
+    fn head_tail_keeps_both_ends() {
+        let mut table = parse_tsv(&numbered(20), "\t");
+        table.head_tail(2);
+        assert_eq!(table.to_string(), "n\n1\n2\n...\n19\n20");
+    }
+
+    #[test]
+    fn head_tail_zero_leaves_only_the_ellipsis() {
+        let mut table = parse_tsv(&numbered(20), "\t");
+        table.head_tail(0);
+        assert_eq!(table.rows, vec![vec![ELLIPSIS.to_owned()]]);
+    }
+
+    #[test]
+    fn head_tail_keeps_quoted_cells_aligned() {
+        let mut table = csv::parse_csv("n\n1\n2\n\"3\"\n4\n5\n", ",", ErrorPolicy::Stop, true);
+        table.head_tail(1);
+        assert_eq!(table.quoted_cells.len(), table.rows.len() + 1);
+        assert_eq!(table.to_string(), "n\n1\n...\n5");
+        table.head_tail(0);
+        assert_eq!(table.quoted_cells.len(), table.rows.len() + 1);
+    }
+
+    #[test]
+    fn explode_splits_a_cell_into_rows() {
+        let mut table = parse_tsv("id\ttags\n1\ta,b\n2\tc\n3\n4\t,d\n", "\t");
+        table.explode(1, ",", false);
+        assert_eq!(
+            table.to_string(),
+            "id\ttags\n1\ta\n1\tb\n2\tc\n3\n4\t\n4\td"
+        );
+
+        let mut table = parse_tsv("id\ttags\n1\t,a,\n2\t\n", "\t");
+        table.explode(1, ",", true);
+        assert_eq!(table.to_string(), "id\ttags\n1\ta\n2\t");
+    }
+
+    #[test]
+    fn implode_joins_values_per_key() {
+        let raw = "id\ttag\n1\tb\n2\tc\n1\ta\n1\tb\n";
+        let mut options = ImplodeOptions {
+            separator: ",".into(),
+            ..Default::default()
+        };
+        let mut table = parse_tsv(raw, "\t");
+        table.implode(0, 1, &options);
+        assert_eq!(table.to_string(), "id\ttag\n1\tb,a,b\n2\tc");
+
+        let mut table = parse_tsv(raw, "\t");
+        options.sort = true;
+        options.unique = true;
+        table.implode(0, 1, &options);
+        assert_eq!(table.to_string(), "id\ttag\n1\ta,b\n2\tc");
+    }
+
+    #[test]
+    fn implode_treats_missing_cells_as_empty() {
+        let mut table = parse_tsv("id\ttag\tn\n1\n1\tx\n\t\t\n", "\t");
+        let options = ImplodeOptions {
+            separator: ";".into(),
+            ..Default::default()
+        };
+        table.implode(0, 1, &options);
+        assert_eq!(table.to_string(), "id\ttag\tn\n1\t;x\n\t\t");
+    }
+
+    #[test]
+    fn reorder_appends_unlisted_columns() {
+        let table = parse_tsv("a\tb\tc\n1\t2\t3\n", "\t");
+        assert_eq!(table.reordered(&[2, 0]).to_string(), "c\ta\tb\n3\t1\t2");
+    }
+
+    #[test]
+    fn clean_whitespace_collapses_runs() {
+        let mut table = parse_tsv(" a  b \n  x\t y\n", "|");
+        table.clean_whitespace();
+        assert_eq!(table.to_string(), "a b\nx y");
+    }
+
+    #[test]
+    fn drop_empty_columns_keeps_any_value() {
+        let mut table = parse_tsv("a\tb\tc\n1\t\t\n\t\t2\n", "\t");
+        table.drop_empty_columns();
+        assert_eq!(table.to_string(), "a\tc\n1\t\n\t2");
+    }
+
+    #[test]
+    fn rotate_wraps_in_both_directions() {
+        let table = parse_tsv("a\tb\tc\n1\t2\t3\n", "\t");
+        assert_eq!(table.rotate_columns(1).to_string(), "b\tc\ta\n2\t3\t1");
+        assert_eq!(table.rotate_columns(-1).to_string(), "c\ta\tb\n3\t1\t2");
+        assert_eq!(table.rotate_columns(3).to_string(), "a\tb\tc\n1\t2\t3");
+    }
+
+    #[test]
+    fn fill_down_copies_the_last_value() {
+        let mut table = parse_tsv("g\tv\n\t0\nx\t1\n\t2\ny\t3\n\t4\n", "\t");
+        table.fill_down(0);
+        assert_eq!(table.to_string(), "g\tv\n\t0\nx\t1\nx\t2\ny\t3\ny\t4");
+    }
+
+    #[test]
+    fn transpose_labels_columns_or_uses_a_key() {
+        let table = parse_tsv("name\tq1\tq2\nann\t1\t2\nbob\t3\t4\n", "\t");
+        assert_eq!(
+            table.transposed(None).to_string(),
+            "column\t1\t2\nname\tann\tbob\nq1\t1\t3\nq2\t2\t4"
+        );
+        assert_eq!(
+            table.transposed(Some(0)).to_string(),
+            "name\tann\tbob\nq1\t1\t3\nq2\t2\t4"
+        );
+
+        let table = parse_tsv("k\tv\nx\t1\nx\t2\n", "\t");
+        assert_eq!(table.transposed(Some(0)).to_string(), "k\tx\tx_2\nv\t1\t2");
+    }
+
+    #[test]
+    fn dedupe_names_numbers_repeats() {
+        let mut names: Vec<String> = vec!["value".into(), "value".into(), "value_2".into()];
+        dedupe_names(&mut names);
+        assert_eq!(names, vec!["value", "value_3", "value_2"]);
+
+        let mut names: Vec<String> = vec!["id".into(), "value".into(), "value".into()];
+        dedupe_names(&mut names);
+        assert_eq!(names, vec!["id", "value", "value_2"]);
+    }
+
+    #[test]
+    fn split_column_by_widths() {
+        let mut table = parse_tsv("id\tdate\n1\t20240115\n", "\t");
+        let names: Vec<String> = vec!["year".into(), "month".into(), "day".into()];
+        table.split_column(1, &[4, 2, 2], &names, false, false);
+        assert_eq!(table.to_string(), "id\tyear\tmonth\tday\n1\t2024\t01\t15");
+
+        let mut table = parse_tsv("date\n20240115\n", "\t");
+        table.split_column(0, &[4, 2, 2], &names, true, false);
+        assert_eq!(
+            table.to_string(),
+            "date\tyear\tmonth\tday\n20240115\t2024\t01\t15"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cell '202401' on row 1 is shorter than the 8 characters to split")]
+    fn strict_split_rejects_short_cells() {
+        let mut table = parse_tsv("date\n202401\n", "\t");
+        let names: Vec<String> = vec!["year".into(), "month".into(), "day".into()];
+        table.split_column(0, &[4, 2, 2], &names, false, true);
+    }
+
+    #[test]
+    fn merge_columns_replaces_the_originals() {
+        let mut table = parse_tsv("id\tfirst\tlast\n1\tAda\tLovelace\n", "\t");
+        table.merge_columns(&[1, 2], "name", " ");
+        assert_eq!(table.to_string(), "id\tname\n1\tAda Lovelace");
+    }
+
+    #[test]
+    fn keep_columns_where_matches_any_or_all() {
+        let raw = "a\tb\tc\n1\tx\t\n2\t3\t\n";
+        let mut table = parse_tsv(raw, "\t");
+        table.keep_columns_where(ColumnMatch::All, |x| x.parse::<f64>().is_ok());
+        assert_eq!(table.columns, vec!["a"]);
+
+        let mut table = parse_tsv(raw, "\t");
+        table.keep_columns_where(ColumnMatch::Any, |x| x.parse::<f64>().is_ok());
+        assert_eq!(table.columns, vec!["a", "b"]);
+    }
+}
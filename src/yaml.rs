@@ -0,0 +1,79 @@
+use crate::{json, stats::parse_number, Table};
+
+fn needs_quotes(value: &str) -> bool {
+    const RESERVED: [&str; 12] = [
+        "true", "false", "yes", "no", "on", "off", "null", "~", "y", "n", "<<", "=",
+    ];
+    value.is_empty()
+        || value.trim() != value
+        || value.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        || value.contains(": ")
+        || value.contains(" #")
+        || value.ends_with(':')
+        || value.contains(|c: char| c.is_control())
+        || RESERVED.contains(&value.to_lowercase().as_str())
+        || parse_number(value).is_some()
+}
+
+pub fn scalar(value: &str) -> String {
+    if needs_quotes(value) {
+        json::escape(value)
+    } else {
+        value.to_owned()
+    }
+}
+
+impl Table {
+    pub fn to_yaml(&self, infer_types: bool) -> String {
+        if self.rows.is_empty() {
+            return "[]".into();
+        }
+        let numeric: Vec<bool> = self.numeric_columns(infer_types);
+        let value = |index: usize, cell: &str| -> String {
+            match numeric.get(index) {
+                Some(true) if cell.is_empty() => "null".into(),
+                Some(true) => cell.trim().to_owned(),
+                _ => scalar(cell),
+            }
+        };
+
+        let mut lines: Vec<String> = Vec::new();
+        for row in &self.rows {
+            for (index, cell) in row.iter().enumerate() {
+                let marker = if index == 0 { "- " } else { "  " };
+                if self.show_header {
+                    let key = self.columns.get(index).map_or("", |x| x.as_str());
+                    lines.push(format!("{}{}: {}", marker, scalar(key), value(index, cell)));
+                } else {
+                    lines.push(format!("{}- {}", marker, value(index, cell)));
+                }
+            }
+            if row.is_empty() {
+                lines.push(if self.show_header { "- {}" } else { "- []" }.into());
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_tsv;
+
+    #[test]
+    fn quotes_values_that_would_change_meaning() {
+        let table = parse_tsv("name\tnote\tcount\nann\ttime: 10:30\t7\nbob\tyes\t\n", "\t");
+        assert_eq!(
+            table.to_yaml(true),
+            "- name: ann\n  note: \"time: 10:30\"\n  count: 7\n\
+             - name: bob\n  note: \"yes\"\n  count: null"
+        );
+    }
+
+    #[test]
+    fn without_a_header_emits_sequences() {
+        let mut table = parse_tsv("a\tb\n1\tx\n", "\t");
+        table.show_header = false;
+        assert_eq!(table.to_yaml(false), "- - \"1\"\n  - x");
+    }
+}
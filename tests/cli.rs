@@ -0,0 +1,616 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+fn cut(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cut"))
+        .args(args)
+        .env_remove("RUST_BACKTRACE")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Couldn't run cut");
+    // cut may exit before reading all of stdin, so a broken pipe is fine.
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(args: &[&str], input: &str) -> String {
+    let output = cut(args, input);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn stderr(args: &[&str], input: &str) -> String {
+    let output = cut(args, input);
+    assert!(!output.status.success(), "expected {:?} to fail", args);
+    String::from_utf8(output.stderr).unwrap()
+}
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cut-{}-{}", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+const TABLE: &str = "name\tage\tcity\nann\t30\tOslo\nbob\t25\tRome\ncyd\t35\tLima\n";
+const DATES: &str = "id\td\tx\n1\t20240115\ta\n2\t20231231\tb\n";
+
+#[test]
+fn source_line_stays_first_after_split_column() {
+    assert_eq!(
+        stdout(
+            &[
+                "--source-line",
+                "--split-column",
+                "d:4,2,2",
+                "--into",
+                "y,m,dd"
+            ],
+            DATES
+        ),
+        "line\tid\ty\tm\tdd\tx\n2\t1\t2024\t01\t15\ta\n3\t2\t2023\t12\t31\tb\n"
+    );
+}
+
+#[test]
+fn source_line_survives_merge_columns() {
+    assert_eq!(
+        stdout(
+            &["--source-line", "--merge-columns", "id,x", "--into", "k"],
+            DATES
+        ),
+        "line\tk\td\n2\t1 a\t20240115\n3\t2 b\t20231231\n"
+    );
+}
+
+#[test]
+fn stats_summarise_the_table() {
+    assert_eq!(
+        stdout(&["--stats"], "n\tname\n1\tann\n3\tbob\n"),
+        "column\tcount\tdistinct\tmin\tmax\tmean\nn\t2\t2\t1\t3\t2\nname\t2\t2\t\t\t\n"
+    );
+}
+
+#[test]
+fn interactive_reads_the_selection_from_stdin() {
+    let path = temp_file("interactive.tsv", TABLE);
+    let output = cut(&["--interactive", path.to_str().unwrap()], "9\n2\n");
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "age\n30\n25\n35\n"
+    );
+    let prompts = String::from_utf8(output.stderr).unwrap();
+    assert!(prompts.contains("  2: age"), "{}", prompts);
+    assert!(
+        prompts.contains("Enter field numbers between 1 and 3"),
+        "{}",
+        prompts
+    );
+}
+
+#[test]
+fn interactive_fails_without_a_selection() {
+    let path = temp_file("interactive-eof.tsv", TABLE);
+    let err = stderr(&["--interactive", path.to_str().unwrap()], "");
+    fs::remove_file(&path).unwrap();
+    assert!(err.contains("No field selection given on stdin"), "{}", err);
+}
+
+#[test]
+fn detect_delim_reports_the_best_candidate_first() {
+    let output = cut(&["--detect-delim"], "a,b,c\n1,2,3\n4,5,6\n");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let report = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(report.lines().next(), Some(",\tfields=3\tconsistency=1.00"));
+}
+
+#[test]
+fn csv_ignores_a_trailing_blank_line() {
+    assert_eq!(stdout(&["--csv"], "a,b\n1,2\n\n"), "a,b\n1,2\n");
+    assert_eq!(stdout(&["--csv", "-f2"], "a,b\n\"x\ny\",2\n"), "b\n2\n");
+}
+
+#[test]
+fn no_header_output_drops_the_header_line() {
+    assert_eq!(
+        stdout(&["--no-header-output", "-f1"], TABLE),
+        "ann\nbob\ncyd\n"
+    );
+    assert_eq!(
+        stdout(
+            &["--no-header-output", "--max-buffer-bytes", "8", "-f1"],
+            TABLE
+        ),
+        "ann\nbob\ncyd\n"
+    );
+    assert_eq!(
+        stdout(&["--no-header-output", "--sort", "age", "-f1"], TABLE),
+        "bob\nann\ncyd\n"
+    );
+}
+
+#[test]
+fn parallel_output_follows_argument_order() {
+    let paths: Vec<PathBuf> = (0..6)
+        .map(|x| temp_file(&format!("parallel-{}.tsv", x), &format!("n\tx\n{}\ta\n", x)))
+        .collect();
+    let mut args: Vec<&str> = vec!["--parallel", "-f1"];
+    args.extend(paths.iter().rev().map(|x| x.to_str().unwrap()));
+    let output = stdout(&args, "");
+    for path in &paths {
+        fs::remove_file(path).unwrap();
+    }
+    assert_eq!(output, "n\n5\nn\n4\nn\n3\nn\n2\nn\n1\nn\n0\n");
+}
+
+#[test]
+fn parallel_reads_stdin_without_files() {
+    assert_eq!(
+        stdout(&["--parallel", "-f1"], TABLE),
+        "name\nann\nbob\ncyd\n"
+    );
+}
+
+#[test]
+fn out_delims_mix_separators() {
+    assert_eq!(
+        stdout(&["--out-delims", "\\t,"], "a\tb\tc\n1\t2\t3\n"),
+        "a\tb,c\n1\t2,3\n"
+    );
+}
+
+#[test]
+fn shell_quote_makes_cells_safe_words() {
+    assert_eq!(
+        stdout(&["--shell-quote", "-f1"], "v\nit's here\n"),
+        "'v'\n'it'\\''s here'\n"
+    );
+}
+
+#[test]
+fn base_zero_numbers_fields_from_zero() {
+    assert_eq!(
+        stdout(&["-f0", "--base", "0"], TABLE),
+        "name\nann\nbob\ncyd\n"
+    );
+    assert_eq!(
+        stdout(&["-f0,2", "--base", "0"], TABLE),
+        stdout(&["-f1,3"], TABLE)
+    );
+    assert!(stderr(&["-f0"], TABLE).contains("Fields are numbered from 1"));
+}
+
+#[test]
+fn assert_rectangular_names_the_input_line() {
+    let err = stderr(&["--assert-rectangular"], "a\tb\n1\t2\n3\n");
+    assert!(
+        err.contains("Table is not rectangular: line 3 has 1 fields but the header has 2"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn on_error_skip_keeps_the_good_rows() {
+    let input = "a,b\n1,2\n\"x\"y,3\n4,5\n";
+    let output = cut(&["--csv", "--on-error", "skip"], input);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a,b\n1,2\n4,5\n");
+    let err = String::from_utf8(output.stderr).unwrap();
+    assert!(err.contains("Skipping malformed CSV on line 3"), "{}", err);
+    assert!(stderr(&["--csv"], input).contains("Malformed CSV on line 3"));
+}
+
+#[test]
+fn fields_between_selects_an_inclusive_block() {
+    assert_eq!(
+        stdout(
+            &["--fields-between", "b", "d"],
+            "a\tb\tc\td\te\n1\t2\t3\t4\t5\n"
+        ),
+        "b\tc\td\n2\t3\t4\n"
+    );
+    assert!(stderr(&["--fields-between", "b", "z"], "a\tb\n").contains("No such column: z"));
+}
+
+#[test]
+fn table_checksum_goes_to_stderr_and_tracks_changes() {
+    let checksum = |input: &str| {
+        let output = cut(&["--table-checksum"], input);
+        assert!(output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    };
+    assert_eq!(checksum(TABLE), checksum(TABLE));
+    assert_eq!(checksum(TABLE).trim().len(), 8);
+    assert_ne!(checksum(TABLE), checksum(&TABLE.replace("Rome", "Roma")));
+}
+
+#[test]
+fn ndjson_streams_the_same_records() {
+    let expected = "{\"name\":\"ann\"}\n{\"name\":\"bob\"}\n{\"name\":\"cyd\"}\n";
+    assert_eq!(stdout(&["--ndjson", "-f1"], TABLE), expected);
+    assert_eq!(
+        stdout(&["--ndjson", "-f1", "--max-buffer-bytes", "8"], TABLE),
+        expected
+    );
+}
+
+#[test]
+fn input_json_reads_an_array_of_objects() {
+    assert_eq!(
+        stdout(
+            &["--input-json", "-Fb,a"],
+            "[{\"a\": 1, \"b\": \"x\"}, {\"a\": 2}]"
+        ),
+        "b\ta\nx\t1\n\t2\n"
+    );
+    assert!(stderr(&["--input-json"], "[{\"a\": 1}] garbage").contains("Invalid JSON on line 1"));
+}
+
+#[test]
+fn reorder_keeps_every_column() {
+    assert_eq!(
+        stdout(&["--reorder", "c,1"], "a\tb\tc\n1\t2\t3\n"),
+        "c\ta\tb\n3\t1\t2\n"
+    );
+}
+
+#[test]
+fn lint_csv_reports_the_line_and_fails() {
+    let output = cut(&["--lint-csv"], "a,b\n1,2\n\"x,3\n4,5\n");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "line 3: unterminated quoted field\n"
+    );
+    assert!(cut(&["--lint-csv"], "a,b\n1,2\n").status.success());
+}
+
+#[test]
+fn sql_insert_needs_a_table_name() {
+    assert_eq!(
+        stdout(
+            &["--sql-insert", "--table", "t", "--infer-types"],
+            "id\tname\n1\tO'Brien\n"
+        ),
+        "INSERT INTO t (id, name) VALUES (1, 'O''Brien');\n"
+    );
+    assert!(stderr(&["--sql-insert"], "a\n1\n").contains("SQL output needs --table NAME"));
+}
+
+#[test]
+fn source_line_survives_filtering() {
+    assert_eq!(
+        stdout(&["--source-line", "--where", "age>26", "-f1"], TABLE),
+        "line\tname\n2\tann\n4\tcyd\n"
+    );
+}
+
+#[test]
+fn source_line_ignores_ragged_cells() {
+    let input = "id\tv\n1\t2\t9\n2\t3\n3\n";
+    assert_eq!(
+        stdout(&["--source-line", "--field-default", "X", "-f1,2,3"], input),
+        "line\tid\tv\t\n2\t1\t2\t9\n3\t2\t3\tX\n4\t3\tX\tX\n"
+    );
+    assert_eq!(
+        stdout(&["--source-line", "--csv", "-f2"], "a,b\n\"x\ny\",1\n,2\n"),
+        "line,b\n2,1\n4,2\n"
+    );
+}
+
+#[test]
+fn ignore_case_prints_the_file_header() {
+    assert_eq!(
+        stdout(&["-FNAME", "--ignore-case"], "id\tName\n1\tann\n"),
+        "Name\nann\n"
+    );
+}
+
+#[test]
+fn missing_names_list_the_available_headers() {
+    let err = stderr(&["-Fname,zip,city,phone"], TABLE);
+    assert!(
+        err.contains("No such columns: zip, phone (available: name, age, city)"),
+        "{}",
+        err
+    );
+    assert_eq!(
+        stdout(&["-Fname,zip", "--allow-missing-fields"], TABLE),
+        "name\nann\nbob\ncyd\n"
+    );
+}
+
+const SEVEN: &str = "n\n1\n2\n3\n4\n5\n6\n7\n";
+
+#[test]
+fn head_tail_only_trims_what_is_shown() {
+    assert_eq!(stdout(&["--head-tail", "1"], SEVEN), "n\n1\n...\n7\n");
+    assert_eq!(
+        stdout(&["--head-tail", "1", "--max-buffer-bytes", "4"], SEVEN),
+        "n\n1\n...\n7\n"
+    );
+    assert_eq!(
+        stdout(&["--head-tail", "1", "--stats"], SEVEN),
+        "column\tcount\tdistinct\tmin\tmax\tmean\nn\t7\t7\t1\t7\t4\n"
+    );
+    assert_eq!(
+        stdout(
+            &["--head-tail", "2", "--value-counts", "n"],
+            "n\na\nb\na\na\nc\n"
+        ),
+        "value\tcount\na\t3\nb\t1\nc\t1\n"
+    );
+    assert!(stdout(&["--head-tail", "1", "--peek"], SEVEN).starts_with("columns: 1\nrows: 7\n"));
+}
+
+#[test]
+fn head_tail_is_rejected_for_machine_formats() {
+    for format in ["--ndjson", "--yaml", "--html"] {
+        let err = stderr(&["--head-tail", "1", format], SEVEN);
+        assert!(err.contains("--head-tail only works with"), "{}", err);
+    }
+}
+
+#[test]
+fn field_default_fills_out_of_range_cells() {
+    assert_eq!(
+        stdout(&["-f1,3", "--field-default", "0"], "a\tb\tc\n1\t2\t3\n4\n"),
+        "a\tc\n1\t3\n4\t0\n"
+    );
+    assert_eq!(
+        stdout(&["-f4", "--field-default", "0"], "a\tb\n1\t2\n"),
+        "\n0\n"
+    );
+    assert!(stderr(&["-f3"], "a\tb\tc\n1\n")
+        .contains("Field 3 is out of range for a row with 1 fields"));
+}
+
+#[test]
+fn output_bom_comes_first() {
+    assert_eq!(stdout(&["--output-bom", "-f1"], "a\n1\n"), "\u{feff}a\n1\n");
+}
+
+#[test]
+fn transpose_key_names_the_new_columns() {
+    assert_eq!(
+        stdout(
+            &["--transpose", "--transpose-key", "name"],
+            "name\tq1\tq2\nann\t1\t2\nbob\t3\t4\n"
+        ),
+        "name\tann\tbob\nq1\t1\t3\nq2\t2\t4\n"
+    );
+}
+
+#[test]
+fn transpose_key_alone_is_rejected_on_every_path() {
+    assert!(
+        stderr(&["--transpose-key", "a"], "a\n1\n").contains("--transpose-key needs --transpose")
+    );
+    assert!(stderr(&["--transpose-key", "a", "-f1"], "a\n1\n")
+        .contains("--transpose-key needs --transpose"));
+}
+
+#[test]
+fn dedupe_headers_makes_names_selectable() {
+    assert_eq!(
+        stdout(&["--dedupe-headers", "-Fvalue_2"], "value\tvalue\n1\t2\n"),
+        "value_2\n2\n"
+    );
+}
+
+#[test]
+fn numeric_rows_drop_stray_text() {
+    let input = "item\tqty\nbolt\t4\nsubtotal\tn/a\nnut\t6\n";
+    assert_eq!(
+        stdout(&["--numeric-rows", "qty"], input),
+        "item\tqty\nbolt\t4\nnut\t6\n"
+    );
+    assert_eq!(
+        stdout(&["--non-numeric-rows", "qty"], input),
+        "item\tqty\nsubtotal\tn/a\n"
+    );
+}
+
+#[test]
+fn validate_reports_lines_and_strict_fails() {
+    let input = "id\n12\nx1\n";
+    let output = cut(&["--validate", "id:^[0-9]+$"], input);
+    assert!(output.status.success());
+    let err = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        err.contains("line 3: id value 'x1' doesn't match ^[0-9]+$"),
+        "{}",
+        err
+    );
+    assert!(!cut(&["--validate", "id:^[0-9]+$", "--strict"], input)
+        .status
+        .success());
+}
+
+#[test]
+fn output_delimiter_auto_mirrors_the_detected_input() {
+    let input = "a;b;c\n1;2;3\n";
+    assert_eq!(
+        stdout(
+            &["--auto-delim", "--output-delimiter", "auto", "-f1,3"],
+            input
+        ),
+        "a;c\n1;3\n"
+    );
+    assert_eq!(
+        stdout(&["-d;", "--sort", "a", "--output-delimiter", "auto"], input),
+        input
+    );
+}
+
+#[test]
+fn preserve_quotes_round_trips_csv() {
+    let input = "id,\"name\"\n1,\"ann\"\n\"2\",bob\n";
+    assert_eq!(stdout(&["--csv", "--preserve-quotes"], input), input);
+}
+
+#[test]
+fn max_buffer_bytes_streams_a_cut_and_refuses_a_sort() {
+    assert_eq!(
+        stdout(&["--max-buffer-bytes", "8", "-f1"], TABLE),
+        "name\nann\nbob\ncyd\n"
+    );
+    assert_eq!(
+        stdout(
+            &["--max-buffer-bytes", "8", "--head-tail", "1", "-f1"],
+            TABLE
+        ),
+        "name\nann\n...\ncyd\n"
+    );
+    let err = stderr(&["--max-buffer-bytes", "8", "--sort", "age"], TABLE);
+    assert!(
+        err.contains(
+            "--sort needs the whole table in memory but the input is over --max-buffer-bytes 8"
+        ),
+        "{}",
+        err
+    );
+    assert_eq!(
+        stdout(
+            &["--max-buffer-bytes", "1000", "--sort", "age", "-f1"],
+            TABLE
+        ),
+        "name\nbob\nann\ncyd\n"
+    );
+}
+
+#[test]
+fn streaming_keeps_a_trailing_empty_row() {
+    assert_eq!(
+        stdout(&["-f1", "--max-buffer-bytes", "2"], "a\n1\n\n"),
+        "a\n1\n\n"
+    );
+}
+
+#[test]
+fn max_line_bytes_rejects_a_long_streamed_line() {
+    let input = format!("{}\tb\n1\t2\n", "a".repeat(500));
+    let err = stderr(
+        &["--max-line-bytes", "10", "--max-buffer-bytes", "5", "-f2"],
+        &input,
+    );
+    assert!(
+        err.contains("Line 1 is longer than --max-line-bytes 10"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn echo_command_records_the_resolved_command_once() {
+    assert_eq!(
+        stdout(&["--echo-command", "--auto-delim", "-f2"], "a;b\n1;2\n"),
+        "# cut --echo-command '-d;' -f2\nb\n2\n"
+    );
+
+    let first = temp_file("echo_first.tsv", "a\tb\n1\t2\n");
+    let second = temp_file("echo_second.tsv", "a\tb\n3\t4\n");
+    let names = (first.to_str().unwrap(), second.to_str().unwrap());
+    let output = stdout(&["--echo-command", "-f1", names.0, names.1], "");
+    assert_eq!(
+        output,
+        format!(
+            "# cut --echo-command -f1 {} {}\na\n1\na\n3\n",
+            names.0, names.1
+        )
+    );
+    fs::remove_file(&first).unwrap();
+    fs::remove_file(&second).unwrap();
+}
+
+#[test]
+fn follow_cuts_rows_appended_to_the_file() {
+    let path = temp_file("follow.tsv", "name\tage\nann\t30\n");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cut"))
+        .args(["--follow", "-f2", path.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Couldn't run cut");
+    let (sender, receiver) = mpsc::channel();
+    let reader = BufReader::new(child.stdout.take().unwrap());
+    thread::spawn(move || {
+        for line in reader.lines() {
+            if sender.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+    let next = || receiver.recv_timeout(Duration::from_secs(5));
+
+    assert_eq!(next(), Ok("age".to_owned()));
+    assert_eq!(next(), Ok("30".to_owned()));
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"bob\t25\n")
+        .unwrap();
+    assert_eq!(next(), Ok("25".to_owned()));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn keep_numeric_columns_drops_mixed_ones() {
+    assert_eq!(
+        stdout(
+            &["--keep-numeric-columns"],
+            "id\tname\tscore\n1\tann\t2.5\n2\t3\tx\n"
+        ),
+        "id\n1\n2\n"
+    );
+    assert_eq!(
+        stdout(
+            &["--keep-columns-containing", "foo", "--column-match", "all"],
+            "a\tb\n1 foo\tx\n2 foo\tfoo\n"
+        ),
+        "a\n1 foo\n2 foo\n"
+    );
+}
+
+#[test]
+fn value_counts_sort_by_count() {
+    assert_eq!(
+        stdout(
+            &["--value-counts", "city"],
+            "city\nOslo\nRome\nOslo\nLima\nRome\nOslo\n"
+        ),
+        "value\tcount\nOslo\t3\nRome\t2\nLima\t1\n"
+    );
+}
+
+#[test]
+fn roundtrip_check_passes_clean_input_and_catches_stray_carriage_returns() {
+    assert_eq!(stdout(&["--roundtrip-check"], TABLE), TABLE);
+    let err = stderr(&["--roundtrip-check"], "a\tb\n1\t2\r\r\n3\t4\n");
+    assert!(
+        err.contains("Round trip changed the table: row 1"),
+        "{}",
+        err
+    );
+}